@@ -3,11 +3,12 @@ extern crate live_reload;
 mod shared_api;
 
 use std::io::Write;
+use std::path::Path;
 
 use std::thread;
 use std::time::Duration;
 
-use live_reload::ShouldQuit;
+use live_reload::ReloadErrorPolicy;
 use shared_api::Host;
 
 type App = live_reload::Reloadable<shared_api::Host>;
@@ -18,13 +19,26 @@ fn print(msg: &str) {
 }
 
 fn main() {
-    let mut app =
-        App::new("target/debug/libreloadable.dylib", Host { print }).expect("Should load!");
-    loop {
-        if app.update() == ShouldQuit::Yes {
-            break;
+    let path = Path::new("target/debug").join(live_reload::library_file_name("reloadable"));
+    let mut app = App::new(path, Host { print }).expect("Should load!");
+    app.set_reload_error_policy(ReloadErrorPolicy::KeepRunning);
+    let mut last_error = None;
+    let code = loop {
+        if let Some(code) = app.update().exit_code() {
+            break code;
         }
         thread::sleep(Duration::from_secs(1));
         app.reload().expect("Should safely reload!");
-    }
+        // The error stays until the library is reloaded, only report it once
+        let error = app.last_error().map(|err| err.to_string());
+        if error != last_error {
+            if let Some(ref err) = error {
+                println!("Reload failed: {}", err);
+            }
+            last_error = error;
+        }
+    };
+    // Exiting skips destructors, so shut the library down first
+    drop(app);
+    std::process::exit(code);
 }
@@ -1,3 +1,5 @@
+// The host only builds this struct, the library is the one calling through it.
+#[allow(dead_code)]
 pub struct Host {
     pub print: fn(&str),
 }
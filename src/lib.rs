@@ -61,6 +61,7 @@
 //! usually look something like this:
 //!
 //! ```rust,no_run
+//! use std::path::Path;
 //! use std::thread;
 //!
 //! mod host_api {
@@ -79,11 +80,11 @@
 //!
 //! fn main() {
 //!     let mut prog = App::new(
-//!         "target/debug/libreload.dylib",
+//!         Path::new("target/debug").join(live_reload::library_file_name("reload")),
 //!         HostApi { print: print },
 //!     ).expect("Should successfully load");
 //!     'main: loop {
-//!         if prog.update() == live_reload::ShouldQuit::Yes {
+//!         if prog.update() != live_reload::ShouldQuit::No {
 //!             break 'main;
 //!         }
 //!         prog.reload().expect("Should successfully reload");
@@ -95,13 +96,16 @@
 //!
 //! A live-reloadable library needs to register its entry-points so that the
 //! host program can find them. The [`live_reload!`][] macro lets you do this
-//! conveniently.
+//! conveniently. For a quick experiment, [`live_reload_simple!`][] only needs
+//! an `update` function.
 //!
 //! The lifecycle of your reloadable library will happen in a few stages:
 //!
 //! - `init` gets called at the very beginning of the program, when the host
 //!   starts for the first time.
-//! - `reload` gets called on each library load, including the first time. This
+//! - `reload` gets called each time the library is reloaded, but not when it's
+//!   first loaded, since `init` is called then instead (see
+//!   [`Builder::reload_on_initial_load`][] to call both). This
 //!   should be usually empty, but when you're in development, you might want to
 //!   reset things here, or migrate data, or things like that. The pointer
 //!   you're passed will refer to the same struct that you had when the previous
@@ -175,350 +179,7093 @@
 //! [`Reloadable`]: struct.Reloadable.html
 //! [`reload`]: struct.Reloadable.html#method.reload
 //! [`live_reload!`]: macro.live_reload.html
+//! [`live_reload_simple!`]: macro.live_reload_simple.html
+//! [`Builder::reload_on_initial_load`]: struct.Builder.html#method.reload_on_initial_load
 
+#[cfg(unix)]
+extern crate libc;
 extern crate libloading;
 extern crate notify;
 
+use std::mem::ManuallyDrop;
 use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::{channel, Receiver};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
 use libloading::Library;
-use notify::{RecommendedWatcher, Watcher};
+use notify::{PollWatcher, RecommendedWatcher, Watcher};
 
-#[cfg(unix)]
-type Symbol<T> = libloading::os::unix::Symbol<T>;
-#[cfg(windows)]
-type Symbol<T> = libloading::os::windows::Symbol<T>;
+pub use notify::DebouncedEvent;
+
+/// The `RELOAD_API` exported by a library, in either of the ABIs it can use.
+enum Api<Host> {
+    Rust(*const internals::ReloadApi<Host>),
+    C(*const internals::ReloadApiC<Host>),
+}
 
 struct AppSym<Host> {
     /// This needs to be present so that the library will be closed on drop
-    _lib: Library,
-    api: Symbol<*mut internals::ReloadApi<Host>>,
+    _lib: ManuallyDrop<Library>,
+    api: Api<Host>,
+    /// The path the library was opened from, which is a copy's path when it
+    /// was loaded from a copy on disk. A library loaded through
+    /// `/proc/self/fd` only has a path while it's being loaded, so for that
+    /// this is the path of the library file.
+    path: PathBuf,
+    /// The file that the library was loaded through `/proc/self/fd` from,
+    /// see `open_library_fd`.
+    fd_file: Option<std::fs::File>,
+    /// Passed to the library's `init`, `reload` and `update`.
+    info: PluginInfo,
+    /// The hash of the library file this was loaded from, see `hash_library`.
+    hash: Option<u64>,
+    /// Keep the library open forever instead, see
+    /// `Reloadable::leak_current_library`.
+    leak: bool,
+}
+
+impl<Host> Drop for AppSym<Host> {
+    fn drop(&mut self) {
+        if !self.leak {
+            unsafe { ManuallyDrop::drop(&mut self._lib) };
+        } else if let Some(file) = self.fd_file.take() {
+            // The leaked library's path has to stay unique too
+            std::mem::forget(file);
+        }
+    }
 }
 
 // @Todo: Flesh out this documentation
 /// A `Reloadable` represents a handle to library that can be live reloaded.
 pub struct Reloadable<Host> {
+    /// The canonical path of the library, as of the last time it was
+    /// resolved.
     path: PathBuf,
+    /// The absolute path of the library as it was given, before resolving any
+    /// links, see `is_trigger`.
+    watched_path: PathBuf,
     sym: Option<AppSym<Host>>,
-    state: Vec<u64>,
-    _watcher: RecommendedWatcher,
-    rx: Receiver<notify::DebouncedEvent>,
-    host: Host,
+    /// Replaced libraries that are kept open for a while, with when they were
+    /// replaced, see `set_previous_library_policy`.
+    previous: Vec<(AppSym<Host>, Instant)>,
+    /// Libraries that get closed and reopened along with the library, see
+    /// `add_dependency`. They're dropped after the library itself.
+    dependencies: Vec<Dependency>,
+    /// Libraries that are reloaded together with the library, see
+    /// `watch_additional_library`.
+    companions: Vec<Companion<Host>>,
+    /// Other files that reload the library, see `add_reload_trigger`.
+    triggers: Vec<ReloadTrigger>,
+    /// A library that passed `validate_candidate`, with the path it's from.
+    candidate: Option<(PathBuf, AppSym<Host>)>,
+    previous_policy: PreviousLibraryPolicy,
+    state: StateBuffer,
+    events: Box<dyn ReloadEvents + Send>,
+    owns_watcher: bool,
+    /// Whether the library's directory may have been removed, which stops the
+    /// default watcher for good, see `rewatch`.
+    watch_lost: bool,
+    watch_mode: WatchMode,
+    library_name: String,
+    host: ManuallyDrop<Host>,
+    /// Whether `into_host` took the host, so it mustn't be dropped.
+    host_taken: bool,
+    verify: bool,
+    trusting: bool,
+    stable_state: bool,
+    max_state_size: usize,
+    auto_compact_state: bool,
+    in_update: bool,
+    held_unloaded: bool,
+    /// Whether `force_quit` ran, so no library is ever loaded again.
+    dead: bool,
+    /// Whether the library's `update` panicked, so its `unload` is skipped.
+    poisoned: bool,
+    needs_init: bool,
+    pending_change: bool,
+    live_slot: usize,
+    leaked: bool,
+    realloc_count: usize,
+    reload_on_initial_load: bool,
+    reload_count: usize,
+    /// What the library asked for with `request_reload`, see
+    /// `take_reload_requests`.
+    reload_requests: ReloadRequest,
+    last_reload: Option<Instant>,
+    last_update_duration: Option<Duration>,
+    min_reload_interval: Duration,
+    build_lock: Option<PathBuf>,
+    /// The number of `SIGUSR1`s handled so far, if reloading on them is enabled.
+    #[cfg(unix)]
+    sigusr1_seen: Option<usize>,
+    error_policy: ReloadErrorPolicy,
+    #[cfg(feature = "count-allocations")]
+    allocation_check: AllocationCheck,
+    #[cfg(feature = "count-allocations")]
+    last_update_allocations: Option<usize>,
+    last_error: Option<Error>,
+    last_reload_timing: Option<ReloadTiming>,
+    timing_callback: Option<TimingCallback>,
+    loaded_callback: Option<LoadedCallback>,
+    state_moved_callback: Option<StateMovedCallback>,
+    should_trigger: Option<TriggerPredicate>,
+    resources: Vec<ReloadResource<Host>>,
+    before_update: Option<HostCallback<Host>>,
     /// On Windows, loading a library may result in temporary errors because of file locking.
     /// Use this field to modify the maximum number of retries and the delay between them.
     pub loading_strategy: LoadingStrategy,
 }
 
-/// Windows specific library loading parameters.
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct LoadingStrategy {
-    /// The maximum number of retry attempts if the loading of the library failed
-    /// with error code 32:
-    /// > The process cannot access the file because it is being used by another process.
+/// Configures and creates a [`Reloadable`][].
+///
+/// [`Reloadable::new`][] is enough for most programs, this is for when some
+/// options need to be set before the library is first loaded.
+///
+/// ```rust,no_run
+/// # struct Host;
+/// let strategy = live_reload::LoadingStrategy {
+///     isolate_symbols: true,
+///     ..Default::default()
+/// };
+/// let app = live_reload::Reloadable::builder("target/debug/libgame.so", Host)
+///     .loading_strategy(strategy)
+///     .build()
+///     .expect("Should successfully load");
+/// ```
+///
+/// [`Reloadable`]: struct.Reloadable.html
+/// [`Reloadable::new`]: struct.Reloadable.html#method.new
+pub struct Builder<Host> {
+    path: PathBuf,
+    host: Host,
+    verify: bool,
+    trusting: bool,
+    stable_state: Option<usize>,
+    reserve_state: usize,
+    max_state_size: usize,
+    state_alignment: StateAlignment,
+    guard_state: bool,
+    skip_init: bool,
+    reload_on_initial_load: bool,
+    loading_strategy: LoadingStrategy,
+    events: Option<Box<dyn ReloadEvents + Send>>,
+    watch_mode: WatchMode,
+    #[cfg(unix)]
+    mmap_state: Option<PathBuf>,
+}
+
+/// A source of filesystem events that tells a [`Reloadable`][] when to reload.
+///
+/// By default a `Reloadable` watches its library's directory with a `notify`
+/// watcher, but a different source can be given to [`Builder::events`][]. This
+/// is mostly useful for tests, which can feed events in deterministically
+/// instead of waiting for the filesystem. A `Receiver` of events implements
+/// this, so a channel works as a mock watcher:
+///
+/// ```rust,no_run
+/// use std::sync::mpsc::channel;
+/// use live_reload::{DebouncedEvent, Reloadable};
+/// # struct Host;
+///
+/// let (tx, rx) = channel();
+/// let mut app = Reloadable::builder("target/debug/libgame.so", Host)
+///     .events(rx)
+///     .build()
+///     .expect("Should successfully load");
+/// let path = std::fs::canonicalize("target/debug/libgame.so").unwrap();
+/// tx.send(DebouncedEvent::Write(path)).unwrap();
+/// // This reloads the library, since it was just told it changed
+/// app.reload().expect("Should successfully reload");
+/// ```
+///
+/// [`Reloadable`]: struct.Reloadable.html
+/// [`Builder::events`]: struct.Builder.html#method.events
+pub trait ReloadEvents {
+    /// Get the next pending event, or `None` if there are no pending events.
     ///
-    /// Default is 20 (21 attempts). This field is used on Windows only.
-    pub max_retries: usize,
-    /// The delay before each retry attempt.
+    /// This must not block.
+    fn try_next(&mut self) -> Option<DebouncedEvent>;
+}
+
+impl ReloadEvents for Receiver<DebouncedEvent> {
+    fn try_next(&mut self) -> Option<DebouncedEvent> {
+        self.try_recv().ok()
+    }
+}
+
+/// How the default event source watches the library's directory.
+///
+/// See [`Builder::watch_mode`][].
+///
+/// [`Builder::watch_mode`]: struct.Builder.html#method.watch_mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum WatchMode {
+    /// Use the platform's native watcher where it works, and poll otherwise.
+    /// This is the default.
     ///
-    /// Default is 100ms. This field is used on Windows only.
-    pub retry_delay: Duration,
+    /// Native watchers don't see changes made on another machine, so on Linux
+    /// a directory on a network or FUSE filesystem (NFS, SMB, SSHFS, and
+    /// similar) is always polled. Anywhere else, if the native watcher can't be
+    /// created or refuses to watch the directory, polling is used instead.
+    #[default]
+    Auto,
+    /// Always use the platform's native watcher, and fail if it doesn't work.
+    Native,
+    /// Always poll the directory for changes, once per second.
+    Poll,
 }
 
-impl Default for LoadingStrategy {
-    fn default() -> Self {
-        Self {
-            max_retries: 20,
-            retry_delay: Duration::from_millis(100),
-        }
+/// The default event source, which watches the library's directory.
+struct NotifyEvents {
+    // Only kept so that the watcher stops when this is dropped
+    _watcher: Box<dyn Send>,
+    rx: Receiver<DebouncedEvent>,
+}
+
+impl NotifyEvents {
+    fn new(path: &Path, mode: WatchMode) -> Result<Self, Error> {
+        let (tx, rx) = channel();
+        let dir = path.parent().unwrap();
+        let delay = Duration::from_secs(1);
+        let native = || -> notify::Result<Box<dyn Send>> {
+            let mut watcher: RecommendedWatcher = Watcher::new(tx.clone(), delay)?;
+            watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+            Ok(Box::new(watcher))
+        };
+        let poll = || -> notify::Result<Box<dyn Send>> {
+            let mut watcher: PollWatcher = Watcher::new(tx.clone(), delay)?;
+            watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+            Ok(Box::new(watcher))
+        };
+        let watcher = match mode {
+            WatchMode::Native => native()?,
+            WatchMode::Poll => poll()?,
+            WatchMode::Auto if is_remote_filesystem(dir) => poll()?,
+            WatchMode::Auto => match native() {
+                Ok(watcher) => watcher,
+                Err(err) => poll().map_err(|_| err)?,
+            },
+        };
+        Ok(NotifyEvents {
+            _watcher: watcher,
+            rx,
+        })
     }
 }
 
-impl LoadingStrategy {
-    /// A reloading strategy that never retries.
-    pub const fn no_retries() -> Self {
-        Self {
-            max_retries: 0,
-            retry_delay: Duration::from_millis(0),
-        }
+/// Check whether `dir` is on a filesystem that native watchers can't see
+/// changes on, because they can happen on another machine.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_remote_filesystem(dir: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    const REMOTE_MAGICS: &[u32] = &[
+        0x6573_5546, // FUSE, which SSHFS and most other remote mounts use
+        0x0000_6969, // NFS
+        0x0000_517b, // SMB
+        0xff53_4d42, // CIFS
+        0xfe53_4d42, // SMB2
+        0x0102_1997, // 9P
+        0x5346_414f, // AFS
+    ];
+    let dir = match CString::new(dir.as_os_str().as_bytes()) {
+        Ok(dir) => dir,
+        Err(_) => return false,
+    };
+    let mut stat = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    if unsafe { libc::statfs(dir.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return false;
     }
+    let fs_type = unsafe { stat.assume_init() }.f_type as u32;
+    REMOTE_MAGICS.contains(&fs_type)
 }
 
-/// The errors that can occur while working with a `Reloadable` object.
-#[derive(Debug)]
-pub enum Error {
-    /// An I/O error occurred while trying to load or reload the library. This
-    /// can indicate that the file is missing, or that the library didn't have
-    /// the expected `RELOAD_API` symbol.
-    // @Diagnostics: Add an error type to distinguish this latter situation
-    Io(std::io::Error),
-    /// An error occurred while creating the filesystem watcher.
-    Watch(notify::Error),
-    /// The `Host` type of the host and library don't match.
-    MismatchedHost,
+/// Check whether `dir` is on a filesystem that native watchers can't see
+/// changes on. Filesystem types can only be detected on Linux.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn is_remote_filesystem(_dir: &Path) -> bool {
+    false
 }
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Error {
-        Error::Io(err)
+impl ReloadEvents for NotifyEvents {
+    fn try_next(&mut self) -> Option<DebouncedEvent> {
+        self.rx.try_next()
     }
 }
 
-impl From<notify::Error> for Error {
-    fn from(err: notify::Error) -> Error {
-        Error::Watch(err)
-    }
+/// The alignment of the buffer holding the library's `State`.
+///
+/// See [`Builder::state_alignment`][].
+///
+/// [`Builder::state_alignment`]: struct.Builder.html#method.state_alignment
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StateAlignment {
+    /// Align the state to 8 bytes. This is the default.
+    #[default]
+    Word,
+    /// Align the state to 64 bytes, the size of a cache line on most CPUs.
+    CacheLine,
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        match *self {
-            Error::Io(ref err) => err.fmt(fmt),
-            Error::Watch(ref err) => err.fmt(fmt),
-            Error::MismatchedHost => "mismatch between host and library's Host types".fmt(fmt),
+/// A chunk of the `State` buffer when it's aligned to a cache line.
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct CacheLine([u8; 64]);
+
+/// The chunks backing a `StateBuffer`, as big as its alignment.
+enum StateChunks {
+    Words(Vec<u64>),
+    CacheLines(Vec<CacheLine>),
+    /// Words in a file mapping, see `Builder::mmap_state`.
+    #[cfg(unix)]
+    Mapped(MappedWords),
+}
+
+/// Run the same code on the `Vec` of a `StateChunks`, whatever its chunk type.
+#[cfg(unix)]
+macro_rules! with_chunks {
+    ($chunks:expr, $vec:ident => $body:expr) => {
+        match $chunks {
+            StateChunks::Words($vec) => $body,
+            StateChunks::CacheLines($vec) => $body,
+            StateChunks::Mapped($vec) => $body,
         }
-    }
+    };
 }
 
-impl<Host> AppSym<Host> {
-    fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let library = Library::new(path.as_ref())?;
-        let api = unsafe {
-            library
-                .get::<*mut internals::ReloadApi<Host>>(b"RELOAD_API")?
-                .into_raw()
+/// Run the same code on the `Vec` of a `StateChunks`, whatever its chunk type.
+#[cfg(not(unix))]
+macro_rules! with_chunks {
+    ($chunks:expr, $vec:ident => $body:expr) => {
+        match $chunks {
+            StateChunks::Words($vec) => $body,
+            StateChunks::CacheLines($vec) => $body,
+        }
+    };
+}
+
+/// Words of a file mapped into memory, with the parts of the `Vec` interface
+/// that a `StateBuffer` uses.
+///
+/// The whole file is always mapped, so the capacity is the length, and
+/// resizing resizes the file and maps it again, which may move it.
+#[cfg(unix)]
+struct MappedWords {
+    file: std::fs::File,
+    ptr: *mut u64,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl MappedWords {
+    /// Map all of the file at `path`, creating it if it doesn't exist.
+    fn open(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let bytes = file.metadata()?.len() as usize;
+        let mut words = MappedWords {
+            file,
+            ptr: std::ptr::NonNull::dangling().as_ptr(),
+            len: 0,
+        };
+        words.map(bytes / std::mem::size_of::<u64>())?;
+        Ok(words)
+    }
+
+    /// Map the first `len` words of the file.
+    fn map(&mut self, len: usize) -> std::io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        if len == 0 {
+            self.ptr = std::ptr::NonNull::dangling().as_ptr();
+            self.len = 0;
+            return Ok(());
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len * std::mem::size_of::<u64>(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                self.file.as_raw_fd(),
+                0,
+            )
         };
-        Ok(AppSym { _lib: library, api })
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+        self.ptr = ptr as *mut u64;
+        self.len = len;
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                libc::munmap(
+                    self.ptr as *mut libc::c_void,
+                    self.len * std::mem::size_of::<u64>(),
+                )
+            };
+        }
+        self.len = 0;
+    }
+
+    fn as_ptr(&self) -> *const u64 {
+        self.ptr
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut u64 {
+        self.ptr
+    }
+
+    fn len(&self) -> usize {
+        self.len
     }
+
+    fn capacity(&self) -> usize {
+        self.len
+    }
+
+    /// Resize the file to `len` words, where new words are zero.
+    fn resize(&mut self, len: usize) -> std::io::Result<()> {
+        if len == self.len {
+            return Ok(());
+        }
+        self.unmap();
+        self.file
+            .set_len((len * std::mem::size_of::<u64>()) as u64)?;
+        self.map(len)
+    }
+
+    /// The whole file is mapped, so there's never any spare capacity.
+    fn reserve_exact(&mut self, _: usize) {}
+
+    fn shrink_to_fit(&mut self) {}
 }
 
-impl<Host> Reloadable<Host> {
-    /// Create a new Reloadable library.
-    ///
-    /// This takes the path to a dynamic library containing a `RELOAD_API`
-    /// symbol that exports the functions needed for live reloading. In order to
-    /// define this symbol in your own reloadable libraries, see the
-    /// [`live_reload!`][] macro. This will load the library and initialize a
-    /// filesystem watcher pointing to the file in order to know when the
-    /// library has changed.
-    ///
-    /// [`live_reload!`]: macro.live_reload.html
-    pub fn new<P: AsRef<Path>>(path: P, host: Host) -> Result<Self, Error> {
-        let sym = Self::load(path.as_ref(), &LoadingStrategy::no_retries())?;
-        let size = (unsafe { &**sym.api }.size)();
-        let (tx, rx) = channel();
-        let mut watcher = notify::watcher(tx, Duration::from_secs(1))?;
-        let mut new_path = PathBuf::new();
-        new_path.push(path);
-        watcher.watch(
-            new_path.parent().unwrap(),
-            notify::RecursiveMode::NonRecursive,
-        )?;
-        let mut app = Reloadable {
-            path: new_path.canonicalize()?,
-            sym: Some(sym),
-            state: Vec::new(),
-            _watcher: watcher,
-            rx,
-            host,
-            loading_strategy: Default::default(),
+#[cfg(unix)]
+impl Drop for MappedWords {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
+
+/// The size of each guard region around a guarded `State`, a whole number of
+/// chunks for either alignment.
+const GUARD_SIZE: usize = 64;
+
+/// The byte the guard regions are filled with.
+const GUARD_BYTE: u8 = 0xA5;
+
+/// The size of the region holding the `StateHeader`, a whole number of
+/// chunks for either alignment.
+const STATE_HEADER_SIZE: usize = 64;
+
+/// The value of `StateHeader::magic`, marking a header that was written by the
+/// host.
+const STATE_MAGIC: u64 = u64::from_le_bytes(*b"LRSTATE\0");
+
+/// The header in front of the `State`, recording which layout the bytes of the
+/// `State` have. A header that's all zeroes hasn't been written yet.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct StateHeader {
+    magic: u64,
+    /// The library's `state_version`.
+    version: u64,
+    /// The size of the `State` the library reported.
+    size: u64,
+}
+
+/// The buffer holding the library's `State`.
+///
+/// The buffer is a `Vec` of chunks as big as its alignment, so sizes are
+/// rounded up to whole chunks. It can start with a region for a `StateHeader`.
+/// When it's guarded, the `State` sits between two regions filled with
+/// `GUARD_BYTE` that the library should never touch. All sizes here are in
+/// bytes and only count the `State` itself.
+struct StateBuffer {
+    chunks: StateChunks,
+    header: usize,
+    guard: usize,
+    /// The size the `State` was last resized to, before rounding.
+    size: usize,
+}
+
+impl StateBuffer {
+    fn new(alignment: StateAlignment, guarded: bool) -> Self {
+        let chunks = match alignment {
+            StateAlignment::Word => StateChunks::Words(Vec::new()),
+            StateAlignment::CacheLine => StateChunks::CacheLines(Vec::new()),
         };
-        app.realloc_buffer(size);
-        if let Some(AppSym { ref mut api, .. }) = app.sym {
-            (unsafe { &***api }.init)(&mut app.host, Self::get_state_ptr(&mut app.state));
+        let guard = if guarded { GUARD_SIZE } else { 0 };
+        StateBuffer {
+            chunks,
+            header: 0,
+            guard,
+            size: 0,
         }
-        Ok(app)
     }
 
-    /// Reload the library if it has changed, otherwise do nothing.
-    ///
-    /// This will consult with the filesystem watcher, and if the library has
-    /// been recreated or updated, it will reload the library. See
-    /// [`reload_now`][] for details on what happens when a library is reloaded.
+    /// Keep the `State` in the file at `path` instead of in memory, along with
+    /// a `StateHeader` in front of it.
     ///
-    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
-    pub fn reload(&mut self) -> Result<(), Error> {
-        let mut should_reload = false;
-        while let Ok(evt) = self.rx.try_recv() {
-            use notify::DebouncedEvent::*;
-            match evt {
-                NoticeWrite(ref path) | Write(ref path) | Create(ref path) => {
-                    if *path.canonicalize()? == self.path {
-                        should_reload = true;
-                    }
-                }
-                _ => {}
+    /// If the file already holds a `State`, the buffer starts out with it.
+    /// There are never guards, so that a file written by a debug build can be
+    /// used by a release build and the other way around.
+    #[cfg(unix)]
+    fn mapped(path: &Path) -> Result<Self, Error> {
+        let mut buffer = StateBuffer {
+            chunks: StateChunks::Mapped(
+                MappedWords::open(path).map_err(io_context("map the state file", path))?,
+            ),
+            header: STATE_HEADER_SIZE,
+            guard: 0,
+            size: 0,
+        };
+        if buffer.raw_len() == 0 {
+            return Ok(buffer);
+        }
+        match buffer.header() {
+            Some(header)
+                if header.magic == STATE_MAGIC
+                    && header.size as usize <= buffer.raw_len() - buffer.overhead() =>
+            {
+                buffer.size = header.size as usize;
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{} doesn't hold a saved State", path.display()),
+                )
+                .into())
             }
         }
+        Ok(buffer)
+    }
 
-        if should_reload || self.sym.is_none() {
-            self.reload_now()
-        } else {
-            Ok(())
+    /// Reserve room for a `StateHeader` in front of the `State`. This has to
+    /// be done before the buffer is first resized.
+    fn with_header(mut self) -> Self {
+        if self.header != 0 {
+            return self;
         }
+        debug_assert_eq!(self.raw_len(), 0);
+        self.header = STATE_HEADER_SIZE;
+        self
     }
 
-    /// Immediately reload the library without checking whether it has changed.
-    ///
-    /// This first calls `unload` on the currently loaded library, then unloads
-    /// the dynamic library. Next, it loads the new dynamic library, and calls
-    /// `reload` on that. If the new library fails to load, this method will
-    /// return an `Err` and the `Reloadable` will be left with no library
-    /// loaded.
-    ///
-    /// [`update`]: struct.Reloadable.html#method.update
-    pub fn reload_now(&mut self) -> Result<(), Error> {
-        if let Some(AppSym { ref mut api, .. }) = self.sym {
-            (unsafe { &***api }.unload)(&mut self.host, Self::get_state_ptr(&mut self.state));
+    /// The offset of the `State` from the start of the allocation.
+    fn offset(&self) -> usize {
+        self.header + self.guard
+    }
+
+    /// The bytes of the allocation that don't belong to the `State`.
+    fn overhead(&self) -> usize {
+        self.header + 2 * self.guard
+    }
+
+    /// Get the header in front of the `State`, if there's room for one.
+    fn header(&self) -> Option<StateHeader> {
+        if self.header == 0 || self.raw_len() < self.header {
+            return None;
         }
-        self.sym = None;
-        let sym = Self::load(&self.path, &self.loading_strategy)?;
-        // @Avoid reallocating if unnecessary
-        self.realloc_buffer((unsafe { &**sym.api }.size)());
-        (unsafe { &**sym.api }.reload)(&mut self.host, Self::get_state_ptr(&mut self.state));
-        self.sym = Some(sym);
+        let base = with_chunks!(&self.chunks, vec => vec.as_ptr() as *const StateHeader);
+        Some(unsafe { base.read() })
+    }
 
-        Ok(())
+    /// Write the header in front of the `State`, if there's room for one.
+    fn write_header(&mut self, version: u64) {
+        if self.header == 0 || self.raw_len() == 0 {
+            return;
+        }
+        let header = StateHeader {
+            magic: STATE_MAGIC,
+            version,
+            size: self.size as u64,
+        };
+        let base = with_chunks!(&mut self.chunks, vec => vec.as_mut_ptr() as *mut StateHeader);
+        unsafe { base.write(header) };
     }
 
-    #[cfg(windows)]
-    fn load(path: &Path, strategy: &LoadingStrategy) -> Result<AppSym<Host>, Error> {
-        let live_path = path.with_extension("live.dll");
-        // Every now and then it seems that the unloading process keeps the dll locked, or
-        // another process spies on it, or the source dll is current being written.
-        // In these cases, we retry a few times before giving up.
-        let mut attempt = 1;
-        'retry: loop {
-            match std::fs::copy(&path, &live_path) {
-                Result::Err(io_err)
-                    if io_err.raw_os_error() == Some(32) && attempt <= strategy.max_retries =>
-                {
-                    std::thread::sleep(strategy.retry_delay);
-                    attempt += 1;
-                    continue 'retry;
-                }
-                Result::Err(io_err) => return Result::Err(io_err.into()),
-                Result::Ok(_) => break 'retry,
-            }
+    fn chunk_size(&self) -> usize {
+        match self.chunks {
+            StateChunks::Words(_) => std::mem::size_of::<u64>(),
+            StateChunks::CacheLines(_) => std::mem::size_of::<CacheLine>(),
+            #[cfg(unix)]
+            StateChunks::Mapped(_) => std::mem::size_of::<u64>(),
         }
-        AppSym::new(&live_path)
     }
 
-    #[cfg(not(windows))]
-    fn load(path: &Path, _strategy: &LoadingStrategy) -> Result<AppSym<Host>, Error> {
-        AppSym::new(&path)
+    /// The size of the whole allocation in use, including the guards.
+    fn raw_len(&self) -> usize {
+        self.chunk_size() * with_chunks!(&self.chunks, vec => vec.len())
     }
 
-    /// Call the update method on the library.
+    fn len(&self) -> usize {
+        self.raw_len().saturating_sub(self.overhead())
+    }
+
+    fn capacity(&self) -> usize {
+        let capacity = self.chunk_size() * with_chunks!(&self.chunks, vec => vec.capacity());
+        capacity.saturating_sub(self.overhead())
+    }
+
+    /// Grow or shrink the buffer to fit `size` bytes, zeroing any new bytes.
     ///
-    /// If no library is currently loaded, this does nothing and returns
-    /// [`ShouldQuit::No`](enum.ShouldQuit.html#).
-    pub fn update(&mut self) -> ShouldQuit {
-        if let Some(AppSym { ref mut api, .. }) = self.sym {
-            (unsafe { &***api }.update)(&mut self.host, Self::get_state_ptr(&mut self.state))
-        } else {
-            ShouldQuit::No
+    /// This can only fail for a buffer in a file.
+    fn resize(&mut self, size: usize) -> std::io::Result<()> {
+        let rounded = size.div_ceil(self.chunk_size()) * self.chunk_size();
+        if self.guard != 0 && self.raw_len() != 0 {
+            // The old trailing guard may become part of the State
+            let old_end = self.offset() + self.len();
+            self.fill(old_end, self.guard, 0);
         }
+        let chunks = (rounded + self.overhead()) / self.chunk_size();
+        match &mut self.chunks {
+            StateChunks::Words(vec) => vec.resize(chunks, 0),
+            StateChunks::CacheLines(vec) => vec.resize(chunks, CacheLine([0; 64])),
+            #[cfg(unix)]
+            StateChunks::Mapped(words) => words.resize(chunks)?,
+        }
+        self.size = size;
+        if self.guard != 0 {
+            self.fill(self.header, self.guard, GUARD_BYTE);
+            self.fill(self.offset() + rounded, self.guard, GUARD_BYTE);
+        }
+        Ok(())
     }
 
-    /// Reallocate the buffer used to store the `State`.
-    fn realloc_buffer(&mut self, size: usize) {
-        let alloc_size_u64s = (size + 7) / 8;
-        self.state.resize(alloc_size_u64s, 0);
+    /// Make sure the buffer can grow to `size` bytes without reallocating.
+    fn reserve(&mut self, size: usize) {
+        let chunks = size.div_ceil(self.chunk_size()) + self.overhead() / self.chunk_size();
+        with_chunks!(&mut self.chunks, vec => {
+            if chunks > vec.len() {
+                vec.reserve_exact(chunks - vec.len());
+            }
+        })
     }
 
-    /// Get a void pointer to the `State` buffer.
-    fn get_state_ptr(buffer: &mut Vec<u64>) -> *mut () {
-        buffer.as_mut_ptr() as *mut ()
+    /// Release any capacity beyond the current size.
+    fn shrink_to_fit(&mut self) {
+        with_chunks!(&mut self.chunks, vec => vec.shrink_to_fit())
     }
 
-    /// Get a reference to the `Host` struct>
-    pub fn host(&self) -> &Host {
-        &self.host
+    fn zero(&mut self) {
+        let (offset, len) = (self.offset(), self.len());
+        self.fill(offset, len, 0);
     }
 
-    /// Get a mutable reference to the `Host` struct.
-    pub fn host_mut(&mut self) -> &mut Host {
-        &mut self.host
+    /// Set `len` bytes of the whole allocation to `byte`, starting at `start`.
+    fn fill(&mut self, start: usize, len: usize, byte: u8) {
+        debug_assert!(start + len <= self.raw_len());
+        let base = with_chunks!(&mut self.chunks, vec => vec.as_mut_ptr() as *mut u8);
+        unsafe { std::ptr::write_bytes(base.add(start), byte, len) };
     }
-}
 
-impl<Host> Drop for Reloadable<Host> {
-    fn drop(&mut self) {
-        if let Some(AppSym { ref mut api, .. }) = self.sym {
-            unsafe {
-                ((***api).deinit)(&mut self.host, Self::get_state_ptr(&mut self.state));
-            }
+    /// Check that nothing wrote over the guards around the `State`.
+    fn guards_intact(&self) -> bool {
+        if self.guard == 0 || self.raw_len() == 0 {
+            return true;
         }
+        let base = with_chunks!(&self.chunks, vec => vec.as_ptr() as *const u8);
+        let bytes = unsafe { std::slice::from_raw_parts(base, self.raw_len()) };
+        let (before, rest) = bytes[self.header..].split_at(self.guard);
+        let after = &rest[rest.len() - self.guard..];
+        before.iter().chain(after).all(|&byte| byte == GUARD_BYTE)
+    }
+
+    fn as_ptr(&self) -> *const () {
+        let base = with_chunks!(&self.chunks, vec => vec.as_ptr() as *const u8);
+        base.wrapping_add(self.offset()) as *const ()
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut () {
+        let base = with_chunks!(&mut self.chunks, vec => vec.as_mut_ptr() as *mut u8);
+        base.wrapping_add(self.offset()) as *mut ()
+    }
+
+    /// The bytes of the `State`, without the rounding up to whole chunks.
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.as_ptr() as *const u8, self.size) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.as_mut_ptr() as *mut u8, self.size) }
     }
 }
 
-/// Should the main program quit? More self-documenting than a boolean!
-///
-/// This type is returned by the [`update`][] method, since with a boolean it's
-/// often unclear if `true` means "should continue" or "should quit".
+/// What [`Reloadable::inspect`][] found in a library.
 ///
-/// [`update`]: struct.Reloadable.html#method.update
-#[derive(Debug, PartialEq, Eq)]
-pub enum ShouldQuit {
-    /// The wrapped library thinks the main program should continue running.
-    No = 0,
-    /// The wrapped library thinks the main program should quit now.
-    Yes = 1,
+/// [`Reloadable::inspect`]: struct.Reloadable.html#method.inspect
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LibraryInfo {
+    /// The path the library was loaded from.
+    pub path: PathBuf,
+    /// The name of the library's package.
+    pub name: String,
+    /// The `live-reload` ABI version the library was built with.
+    pub abi_version: u32,
+    /// The size of the library's `State` type in bytes.
+    pub state_size: usize,
+    /// The size of the library's init arguments type in bytes.
+    pub init_args_size: usize,
+    /// Whether the library's `Host` type matches the host program's.
+    pub host_matches: bool,
 }
 
-/// Exported for compilation reasons but not useful, only look if you're curious.
+/// Each check that [`Reloadable::diagnose`][] made on a library.
 ///
-/// This module holds to the `ReloadApi` struct, which is what what is looked up
-/// by the `Reloadable` in order to communicate with the reloadable library. It
-/// needs to be exported in order to avoid forcing the type definition into the
-/// pub symbols of the wrapped library. An instance of `ReloadApi` called
-/// `RELOAD_API` is generated by the [`live_reload!`][] macro.
+/// The checks happen in order, and a check that couldn't be made because an
+/// earlier one failed is left as `false` or `None`.
 ///
-/// [`live_reload!`]: ../macro.live_reload.html
-pub mod internals {
+/// [`Reloadable::diagnose`]: struct.Reloadable.html#method.diagnose
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct LoadDiagnostics {
+    /// The path that was checked.
+    pub path: PathBuf,
+    /// Whether the path is a file.
+    pub file_exists: bool,
+    /// Whether the file is an rlib, which is what Cargo builds without
+    /// `crate-type = ["cdylib"]`.
+    pub is_rlib: bool,
+    /// Why the file couldn't be opened as a dynamic library, if it couldn't.
+    pub open_error: Option<String>,
+    /// Whether the library exports a `RELOAD_API` symbol.
+    pub has_reload_api: bool,
+    /// Whether `RELOAD_API` was made by [`live_reload!`][], using either the
+    /// Rust or the C protocol.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    pub is_reload_api: bool,
+    /// The `live-reload` ABI version the library was built with.
+    pub abi_version: Option<u32>,
+    /// Whether the ABI version is the one this host was built with. Nothing
+    /// after this is checked if it isn't, since the rest of `RELOAD_API` can't
+    /// be read.
+    pub abi_matches: bool,
+    /// Whether the library's `Host` type has the size and alignment of the
+    /// host program's.
+    pub host_matches: bool,
+    /// The name of the library's package.
+    pub name: Option<String>,
+    /// The size of the library's `State` type in bytes.
+    pub state_size: Option<usize>,
+    /// The size of the library's init arguments type in bytes.
+    pub init_args_size: Option<usize>,
+}
+
+impl LoadDiagnostics {
+    /// Check whether every check passed, so that the library would load.
+    pub fn would_load(&self) -> bool {
+        self.is_reload_api && self.abi_matches && self.host_matches
+    }
+}
+
+/// How long each phase of the last reload took.
+///
+/// See [`Reloadable::last_reload_timing`][].
+///
+/// [`Reloadable::last_reload_timing`]: struct.Reloadable.html#method.last_reload_timing
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ReloadTiming {
+    /// The time spent in the old library's `unload` function.
+    pub unload: Duration,
+    /// The time spent unloading the old dynamic library.
+    pub dlclose: Duration,
+    /// The time spent loading and checking the new dynamic library, including
+    /// copying it first on Windows.
+    pub dlopen: Duration,
+    /// The time spent in the new library's `reload` function.
+    pub reload_hook: Duration,
+}
+
+/// A snapshot of a `Reloadable`'s counters and status, for showing in a
+/// development overlay.
+///
+/// See [`Reloadable::stats`][].
+///
+/// [`Reloadable::stats`]: struct.Reloadable.html#method.stats
+#[derive(Clone, Debug)]
+pub struct ReloadStats {
+    /// How many times the library has been reloaded, see
+    /// [`Reloadable::reload_count`][].
+    ///
+    /// [`Reloadable::reload_count`]: struct.Reloadable.html#method.reload_count
+    pub reload_count: usize,
+    /// How many times a reload changed the size of the `State` buffer's
+    /// allocation, see [`Reloadable::realloc_count`][].
+    ///
+    /// [`Reloadable::realloc_count`]: struct.Reloadable.html#method.realloc_count
+    pub realloc_count: usize,
+    /// When the library was last reloaded, if it has been.
+    pub last_reload: Option<Instant>,
+    /// How long the last call to the library's `update` took, if it has been
+    /// called.
+    pub last_update_duration: Option<Duration>,
+    /// The message of the error from the last failed reload, see
+    /// [`Reloadable::last_error`][].
+    ///
+    /// [`Reloadable::last_error`]: struct.Reloadable.html#method.last_error
+    pub last_error: Option<String>,
+    /// Whether a library is currently loaded.
+    pub is_loaded: bool,
+}
+
+/// A library that's reopened on every reload, see
+/// [`Reloadable::add_dependency`][].
+///
+/// [`Reloadable::add_dependency`]: struct.Reloadable.html#method.add_dependency
+struct Dependency {
+    path: PathBuf,
+    library: Option<Library>,
+}
+
+/// A file whose changes reload the library, see
+/// [`Reloadable::add_reload_trigger`][].
+///
+/// [`Reloadable::add_reload_trigger`]: struct.Reloadable.html#method.add_reload_trigger
+struct ReloadTrigger {
+    path: PathBuf,
+    /// Watches the file's directory, if it isn't the library's.
+    events: Option<NotifyEvents>,
+}
+
+/// A library that's reloaded together with the library, see
+/// [`Reloadable::watch_additional_library`][].
+///
+/// [`Reloadable::watch_additional_library`]: struct.Reloadable.html#method.watch_additional_library
+struct Companion<Host> {
+    path: PathBuf,
+    sym: Option<AppSym<Host>>,
+    /// The companion's `State`, which is kept across reloads.
+    state: Vec<u64>,
+    live_slot: usize,
+    /// Watches the companion's directory, if it isn't the library's.
+    events: Option<NotifyEvents>,
+}
+
+impl<Host> Companion<Host> {
+    /// Load a new copy of the companion, which can be open at the same time
+    /// as the old one. Its `State` can't be bigger than `max_state_size`, like
+    /// the library's. The next copy only gets the other name once this one is
+    /// installed, since the loader would hand back the old library if a copy
+    /// that was dropped took its name.
+    fn load(
+        &mut self,
+        strategy: &LoadingStrategy,
+        verify: bool,
+        max_state_size: usize,
+    ) -> Result<AppSym<Host>, Error> {
+        let hash = hash_library(&self.path).ok();
+        let mut sym = load_live_copy(&self.path, self.live_slot, strategy)?;
+        sym.hash = hash;
+        if verify {
+            sym.verify()?;
+        }
+        if sym.init_args_size() != 0 {
+            return Err(Error::MismatchedInitArgs);
+        }
+        if sym.state_size() > max_state_size {
+            return Err(Error::StateTooLarge {
+                max: max_state_size,
+                requested: sym.state_size(),
+            });
+        }
+        sym.info = PluginInfo::for_library(&self.path);
+        Ok(sym)
+    }
+
+    /// Check whether the loaded companion has the same contents as its file.
+    fn is_unchanged(&self) -> bool {
+        match self.sym {
+            Some(ref sym) => sym.hash.is_some() && sym.hash == hash_library(&self.path).ok(),
+            None => false,
+        }
+    }
+
+    /// Switch to `sym` and call its `reload`, after resizing the state for it.
+    fn install(&mut self, sym: AppSym<Host>, host: &mut Host, error: &mut Option<String>) {
+        let old_base = self.state.as_ptr() as usize;
+        self.state.resize(sym.state_size().div_ceil(8), 0);
+        let state = self.state.as_mut_ptr() as *mut ();
+        if let Some(moved) = sym.state_moved() {
+            if state as usize != old_base {
+                moved(host, state, old_base, state as usize);
+            }
+        }
+        sym.reload(host, state, error);
+        self.sym = Some(sym);
+        self.live_slot = 1 - self.live_slot;
+    }
+}
+
+type TimingCallback = Box<dyn FnMut(&ReloadTiming) + Send>;
+
+type LoadedCallback = Box<dyn FnMut(bool) + Send>;
+
+type StateMovedCallback = Box<dyn FnMut(usize, usize) + Send>;
+
+type TriggerPredicate = Box<dyn Fn(&Path) -> bool + Send>;
+
+type HostCallback<Host> = Box<dyn FnMut(&mut Host) + Send>;
+
+/// A host resource that gets released before the library is unloaded and
+/// acquired again once the new library is loaded.
+struct ReloadResource<Host> {
+    key: String,
+    release: HostCallback<Host>,
+    acquire: HostCallback<Host>,
+}
+
+/// A host that was swapped into a `Reloadable` for one update, and is
+/// swapped back out when this is dropped, see `Reloadable::update_with_host`.
+struct BorrowedHost<'a, Host> {
+    reloadable: &'a mut Reloadable<Host>,
+    host: &'a mut Host,
+}
+
+impl<'a, Host> BorrowedHost<'a, Host> {
+    fn new(reloadable: &'a mut Reloadable<Host>, host: &'a mut Host) -> Self {
+        std::mem::swap(&mut *reloadable.host, host);
+        BorrowedHost { reloadable, host }
+    }
+}
+
+impl<'a, Host> Drop for BorrowedHost<'a, Host> {
+    fn drop(&mut self) {
+        // This also runs when the update unwinds, so the caller's host is
+        // never left behind in the `Reloadable`
+        std::mem::swap(&mut *self.reloadable.host, self.host);
+    }
+}
+
+/// What [`update`][] should do when it allocates on the heap.
+///
+/// This needs the `count-allocations` feature, see
+/// [`Reloadable::set_allocation_check`][].
+///
+/// [`update`]: struct.Reloadable.html#method.update
+/// [`Reloadable::set_allocation_check`]: struct.Reloadable.html#method.set_allocation_check
+#[cfg(feature = "count-allocations")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum AllocationCheck {
+    /// Don't count allocations. This is the default.
+    #[default]
+    Off,
+    /// Count the allocations of every update, see
+    /// [`Reloadable::last_update_allocations`][].
+    ///
+    /// [`Reloadable::last_update_allocations`]: struct.Reloadable.html#method.last_update_allocations
+    Count,
+    /// Panic when an update allocated.
+    Panic,
+}
+
+/// What [`reload`][] should do when reloading the library fails.
+///
+/// [`reload`]: struct.Reloadable.html#method.reload
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ReloadErrorPolicy {
+    /// Return the error from [`reload`][]. This is the default.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    #[default]
+    Abort,
+    /// Store the error so it can be queried with [`last_error`][], and return
+    /// `Ok(())` so the main loop keeps running on the old library.
+    ///
+    /// [`last_error`]: struct.Reloadable.html#method.last_error
+    KeepRunning,
+}
+
+/// What [`reload_now`][] did.
+///
+/// [`reload_now`]: struct.Reloadable.html#method.reload_now
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReloadOutcome {
+    /// The library was reloaded.
+    Reloaded,
+    /// The library file has the same contents as the loaded library, so it
+    /// wasn't reloaded.
+    Unchanged,
+}
+
+/// How [`reload_now_with`][] treats the currently loaded library.
+///
+/// [`reload_now_with`]: struct.Reloadable.html#method.reload_now_with
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RecoveryMode {
+    /// Reload like [`reload_now`][]: the old library's `unload` is called,
+    /// unless its `update` panicked.
+    ///
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    #[default]
+    Normal,
+    /// Close the old library without calling its `unload`, and load the
+    /// library again even if the file is unchanged. Use this when the host
+    /// knows the library is broken, for example when `update` keeps reporting
+    /// that its state is corrupt.
+    SkipUnload,
+}
+
+/// What happened in one call to [`Reloadable::poll`][].
+///
+/// [`Reloadable::poll`]: struct.Reloadable.html#method.poll
+#[derive(Debug)]
+pub struct FramePoll {
+    /// Whether the library was reloaded before `update` ran.
+    pub reloaded: bool,
+    /// What the library's `update` returned. This is `ShouldQuit::No` if it
+    /// reported an error, or if no library is loaded.
+    pub quit: ShouldQuit,
+    /// The error from the reload, or else from `update`, if either failed.
+    pub error: Option<Error>,
+    /// What the library asked the host to do, see [`request_reload`][].
+    ///
+    /// [`request_reload`]: fn.request_reload.html
+    pub reload_requests: ReloadRequest,
+}
+
+/// What happens to the old library after a reload, see
+/// [`Reloadable::set_previous_library_policy`][].
+///
+/// [`Reloadable::set_previous_library_policy`]: struct.Reloadable.html#method.set_previous_library_policy
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PreviousLibraryPolicy {
+    /// Close the old library as soon as the new one is loaded. This is the
+    /// default.
+    #[default]
+    Close,
+    /// Keep the old library open for this long after it was replaced.
+    KeepFor(Duration),
+    /// Keep the old library open until
+    /// [`Reloadable::release_previous`][] is called.
+    ///
+    /// [`Reloadable::release_previous`]: struct.Reloadable.html#method.release_previous
+    KeepUntilReleased,
+}
+
+/// When the dynamic loader resolves the symbols a library uses from other
+/// libraries, see [`LoadingStrategy::binding`][].
+///
+/// [`LoadingStrategy::binding`]: struct.LoadingStrategy.html#structfield.binding
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SymbolBinding {
+    /// Resolve every symbol while loading the library (`RTLD_NOW`), so a
+    /// missing symbol fails the load. This is the default.
+    #[default]
+    Now,
+    /// Resolve functions the first time they're called (`RTLD_LAZY`), which
+    /// makes loading a large library faster, but turns a missing symbol into a
+    /// crash whenever it's first used.
+    Lazy,
+}
+
+/// Library loading parameters.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LoadingStrategy {
+    /// The maximum number of retry attempts if the loading of the library failed
+    /// with error code 32:
+    /// > The process cannot access the file because it is being used by another process.
+    ///
+    /// Default is 20 (21 attempts). This field is used on Windows only.
+    pub max_retries: usize,
+    /// The delay before each retry attempt.
+    ///
+    /// Default is 100ms. This field is used on Windows only.
+    pub retry_delay: Duration,
+    /// Load the library with `RTLD_LOCAL`, so that its symbols aren't used to
+    /// resolve symbols in libraries loaded later.
+    ///
+    /// This keeps each reloadable library's symbols private, so that two
+    /// libraries sharing a dependency don't end up bound to each other's
+    /// copies, which would dangle once the other one reloads. The platform
+    /// default is `RTLD_LOCAL` on Linux but `RTLD_GLOBAL` on macOS.
+    ///
+    /// Default is false, which uses the platform default. This field is used
+    /// on Unix only.
+    pub isolate_symbols: bool,
+    /// When to resolve the symbols the library uses from other libraries.
+    ///
+    /// Default is [`SymbolBinding::Now`][]. This field is used on Unix only.
+    ///
+    /// [`SymbolBinding::Now`]: enum.SymbolBinding.html#variant.Now
+    pub binding: SymbolBinding,
+    /// Load and check the new library before unloading the old one.
+    ///
+    /// The old library's `unload` only runs once the new library is known to
+    /// load, so a broken build leaves the old library running instead of
+    /// leaving no library loaded. Since a library can't be loaded twice from
+    /// the same path, the new library is loaded from a copy next to it, which
+    /// means briefly having both in memory.
+    ///
+    /// Default is false.
+    pub load_new_first: bool,
+    /// The number of times to retry loading the library if it fails to open.
+    ///
+    /// Right after a build, the library can fail to load because the linker
+    /// is still writing it. Retrying a moment later usually succeeds.
+    ///
+    /// Default is 0. This field is used on all platforms.
+    pub load_retries: usize,
+    /// The delay before the first load retry, which doubles for each retry
+    /// after it.
+    ///
+    /// Default is 10ms.
+    pub load_retry_delay: Duration,
+    /// Load the library file itself instead of a copy of it, where the
+    /// platform supports it.
+    ///
+    /// With [`load_new_first`][], or after the library was leaked, each
+    /// reload loads a copy of the library, which normally means writing the
+    /// whole library to a file next to it. On Linux and Android, this opens
+    /// the library file instead and loads it through `/proc/self/fd`, so the
+    /// loader maps the build itself copy-on-write, like it maps any library,
+    /// and nothing is copied. The file stays open while the library is loaded,
+    /// so the loaded build is kept even after the next build replaces it. Like
+    /// with any loaded library, a build has to replace the file instead of
+    /// writing over it, which the linker already does.
+    ///
+    /// If that same file is still loaded, for example when a library that
+    /// panicked is reloaded without being rebuilt, the loader would hand back
+    /// the open library, so it's copied into an in-memory file instead. If
+    /// neither works, or on any other platform, the copy is written to disk as
+    /// usual. The first load never copies the library, except on Windows,
+    /// where this has no effect.
+    ///
+    /// Default is false.
+    ///
+    /// [`load_new_first`]: struct.LoadingStrategy.html#structfield.load_new_first
+    pub map_original: bool,
+    /// The names of other symbols that the library has to export, besides
+    /// `RELOAD_API`.
+    ///
+    /// If the host looks up more of its own symbols in the library, listing
+    /// them here makes a library that's missing one fail to load with
+    /// [`Error::MissingSymbol`][], instead of failing later when the host
+    /// looks it up.
+    ///
+    /// Default is empty.
+    ///
+    /// [`Error::MissingSymbol`]: enum.Error.html#variant.MissingSymbol
+    pub required_symbols: Vec<String>,
+    /// Whether to skip checking that `RELOAD_API` starts with the magic
+    /// number that [`live_reload!`][] writes. Without the check, anything
+    /// exported as `RELOAD_API` is taken to be made by `live_reload!`, so
+    /// this is only safe for libraries that are known to be, see
+    /// [`Builder::trusting`][].
+    ///
+    /// Default is false.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Builder::trusting`]: struct.Builder.html#method.trusting
+    pub skip_magic_check: bool,
+    /// The directory to put the copies of the library in, instead of next to
+    /// the library, see [`Builder::copy_dir`][].
+    ///
+    /// Default is `None`.
+    ///
+    /// [`Builder::copy_dir`]: struct.Builder.html#method.copy_dir
+    pub copy_dir: Option<PathBuf>,
+}
+
+impl Default for LoadingStrategy {
+    fn default() -> Self {
+        Self {
+            max_retries: 20,
+            retry_delay: Duration::from_millis(100),
+            isolate_symbols: false,
+            binding: SymbolBinding::Now,
+            load_new_first: false,
+            load_retries: 0,
+            load_retry_delay: Duration::from_millis(10),
+            map_original: false,
+            required_symbols: Vec::new(),
+            skip_magic_check: false,
+            copy_dir: None,
+        }
+    }
+}
+
+impl LoadingStrategy {
+    /// A reloading strategy that never retries.
+    pub const fn no_retries() -> Self {
+        Self {
+            max_retries: 0,
+            retry_delay: Duration::from_millis(0),
+            isolate_symbols: false,
+            binding: SymbolBinding::Now,
+            load_new_first: false,
+            load_retries: 0,
+            load_retry_delay: Duration::from_millis(0),
+            map_original: false,
+            required_symbols: Vec::new(),
+            skip_magic_check: false,
+            copy_dir: None,
+        }
+    }
+
+    /// Where to copy the library at `path` to load it, with its extension
+    /// replaced by `extension`.
+    fn live_path(&self, path: &Path, extension: &str) -> PathBuf {
+        let live_path = path.with_extension(extension);
+        match (&self.copy_dir, live_path.file_name()) {
+            (Some(dir), Some(name)) => dir.join(name),
+            _ => live_path,
+        }
+    }
+
+    /// Retry loading the library if it fails to open, according to
+    /// [`load_retries`][] and [`load_retry_delay`][].
+    ///
+    /// [`load_retries`]: struct.LoadingStrategy.html#structfield.load_retries
+    /// [`load_retry_delay`]: struct.LoadingStrategy.html#structfield.load_retry_delay
+    fn retry_load<T>(&self, mut load: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+        let mut delay = self.load_retry_delay;
+        let mut retries = self.load_retries;
+        loop {
+            match load() {
+                Err(Error::Io(_)) if retries > 0 => {
+                    std::thread::sleep(delay);
+                    delay *= 2;
+                    retries -= 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Open a dynamic library with the platform flags chosen by the strategy.
+#[cfg(unix)]
+fn open_library(path: &Path, strategy: &LoadingStrategy) -> std::io::Result<Library> {
+    let mut flags = match strategy.binding {
+        SymbolBinding::Now => libc::RTLD_NOW,
+        SymbolBinding::Lazy => libc::RTLD_LAZY,
+    };
+    if strategy.isolate_symbols {
+        flags |= libc::RTLD_LOCAL;
+    }
+    libloading::os::unix::Library::open(Some(path), flags).map(Library::from)
+}
+
+/// Open a dynamic library with the platform flags chosen by the strategy.
+#[cfg(not(unix))]
+fn open_library(path: &Path, _strategy: &LoadingStrategy) -> std::io::Result<Library> {
+    Library::new(path)
+}
+
+/// The errors that can occur while working with a `Reloadable` object.
+///
+/// Each error displays as a sentence, including the message of the error that
+/// caused it, if any:
+///
+/// ```rust
+/// use std::io::{Error as IoError, ErrorKind};
+/// use std::path::PathBuf;
+/// use live_reload::Error;
+///
+/// let path = PathBuf::from("target/debug/libgame.so");
+/// let messages = [
+///     (
+///         Error::Io(IoError::new(
+///             ErrorKind::NotFound,
+///             "couldn't load target/debug/libgame.so: no such file",
+///         )),
+///         "I/O error: couldn't load target/debug/libgame.so: no such file",
+///     ),
+///     (
+///         Error::MissingReloadApi(path.clone()),
+///         "target/debug/libgame.so doesn't export RELOAD_API, make sure it's \
+///          built with crate-type = [\"cdylib\"] and uses live_reload!",
+///     ),
+///     (
+///         Error::NotAReloadApi(path.clone()),
+///         "target/debug/libgame.so exports a RELOAD_API that wasn't made by live_reload!",
+///     ),
+///     (
+///         Error::NotADynamicLibrary(path),
+///         "target/debug/libgame.so is an rlib, not a dynamic library, build it \
+///          with crate-type = [\"cdylib\"]",
+///     ),
+///     (
+///         Error::Watch(notify::Error::Generic("too many watches".into())),
+///         "error while watching the library for changes: too many watches",
+///     ),
+///     (
+///         Error::MismatchedHost,
+///         "mismatch between host and library's Host types",
+///     ),
+///     (
+///         Error::MismatchedAbi {
+///             expected: 2,
+///             found: 1,
+///         },
+///         "library was built for live-reload ABI version 1, but the host uses version 2",
+///     ),
+///     (
+///         Error::QuitRequestedAtInit(3),
+///         "the library asked to quit with code 3 during init",
+///     ),
+///     (Error::NotLoaded, "no library is loaded"),
+///     (
+///         Error::ReentrantReload,
+///         "can't reload the library while it's updating",
+///     ),
+///     (
+///         Error::StateWouldMove {
+///             capacity: 64,
+///             requested: 128,
+///         },
+///         "the State needs 128 bytes, but only 64 bytes were reserved for it",
+///     ),
+///     (
+///         Error::StateTooLarge {
+///             max: 64,
+///             requested: 128,
+///         },
+///         "the State needs 128 bytes, which is more than the limit of 64 bytes",
+///     ),
+///     (
+///         Error::MismatchedInput,
+///         "mismatch between host and library's input types",
+///     ),
+///     (
+///         Error::MismatchedInitArgs,
+///         "mismatch between host and library's init argument types",
+///     ),
+///     (
+///         Error::MismatchedStateSize {
+///             expected: 8,
+///             found: 4,
+///         },
+///         "expected 8 bytes of State, but got 4 bytes",
+///     ),
+///     (
+///         Error::MissingFunction("update_incremental"),
+///         "the library doesn't provide an update_incremental function",
+///     ),
+///     (
+///         Error::MissingSymbol("PLUGIN_INFO".into()),
+///         "the library doesn't export the required symbol PLUGIN_INFO",
+///     ),
+///     (
+///         Error::NoCandidate,
+///         "no candidate library passed validation",
+///     ),
+///     (
+///         Error::Library("level 11 doesn't exist".into()),
+///         "the library reported an error: level 11 doesn't exist",
+///     ),
+///     (
+///         Error::Panicked("index out of bounds".into()),
+///         "the library panicked: index out of bounds",
+///     ),
+/// ];
+/// for (error, message) in &messages {
+///     assert_eq!(error.to_string(), *message);
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O error occurred, for example while loading or copying the library
+    /// or mapping the state file. This can indicate that the file is missing
+    /// or isn't a valid library. Where it's known, the message says what was
+    /// being done to which file, like "couldn't load
+    /// target/debug/libgame.so: ...".
+    Io(std::io::Error),
+    /// The library loaded, but it doesn't export the `RELOAD_API` symbol.
+    ///
+    /// This usually means the library wasn't built with
+    /// `crate-type = ["cdylib"]`, or it doesn't invoke the [`live_reload!`][]
+    /// macro.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    MissingReloadApi(PathBuf),
+    /// The library exports a `RELOAD_API` symbol, but it wasn't made by
+    /// [`live_reload!`][], so it can't be used.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    NotAReloadApi(PathBuf),
+    /// The file is a static Rust library (an rlib) rather than a dynamic
+    /// library, so it can't be loaded. Build it with
+    /// `crate-type = ["cdylib"]` instead.
+    NotADynamicLibrary(PathBuf),
+    /// An error occurred while creating the filesystem watcher, or the watcher
+    /// reported an error while watching.
+    Watch(notify::Error),
+    /// The `Host` type of the host and library don't match.
+    ///
+    /// This is detected by comparing the size and alignment of `Host` on both
+    /// sides, see [`Reloadable::new_unchecked`][] for details.
+    ///
+    /// [`Reloadable::new_unchecked`]: struct.Reloadable.html#method.new_unchecked
+    MismatchedHost,
+    /// The library was built against a version of `live-reload` with an
+    /// incompatible `RELOAD_API` layout.
+    MismatchedAbi {
+        /// The ABI version of the host program.
+        expected: u32,
+        /// The ABI version of the library.
+        found: u32,
+    },
+    /// The library's `init` asked to quit while creating the `Reloadable`, or
+    /// while a reload reset the `State`, meaning it can't run. This holds the
+    /// [`exit_code`][] it asked for.
+    ///
+    /// [`exit_code`]: enum.ShouldQuit.html#method.exit_code
+    QuitRequestedAtInit(i32),
+    /// The operation needs a loaded library, but none is loaded.
+    NotLoaded,
+    /// A reload was requested while the library's `update` was running.
+    ReentrantReload,
+    /// The `State` grew past the space reserved by
+    /// [`Builder::stable_state`][], so it would have to move.
+    ///
+    /// [`Builder::stable_state`]: struct.Builder.html#method.stable_state
+    StateWouldMove {
+        /// The number of bytes reserved for the `State`.
+        capacity: usize,
+        /// The size of the new `State` in bytes.
+        requested: usize,
+    },
+    /// The library's `State` is bigger than [`Builder::max_state_size`][]
+    /// allows. This usually means the library isn't what it claims to be.
+    ///
+    /// [`Builder::max_state_size`]: struct.Builder.html#method.max_state_size
+    StateTooLarge {
+        /// The largest allowed size of the `State` in bytes.
+        max: usize,
+        /// The size of the new `State` in bytes.
+        requested: usize,
+    },
+    /// The input events passed to [`Reloadable::update_with_input`][] don't
+    /// match the `input` type the library declared in [`live_reload!`][].
+    ///
+    /// [`Reloadable::update_with_input`]: struct.Reloadable.html#method.update_with_input
+    /// [`PluginInfo`]: struct.PluginInfo.html
+    /// [`live_reload!`]: macro.live_reload.html
+    MismatchedInput,
+    /// The init arguments passed by the host don't match the `init_args` type
+    /// the library declared in [`live_reload!`][].
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    MismatchedInitArgs,
+    /// The bytes passed to [`Reloadable::replace_state_bytes`][] aren't the
+    /// same size as the `State`.
+    ///
+    /// [`Reloadable::replace_state_bytes`]: struct.Reloadable.html#method.replace_state_bytes
+    MismatchedStateSize {
+        /// The size of the `State` in bytes.
+        expected: usize,
+        /// The number of bytes that were passed.
+        found: usize,
+    },
+    /// The library doesn't provide the optional function with this name, which
+    /// the host tried to call.
+    MissingFunction(&'static str),
+    /// The library doesn't export this symbol from
+    /// [`LoadingStrategy::required_symbols`][].
+    ///
+    /// [`LoadingStrategy::required_symbols`]: struct.LoadingStrategy.html#structfield.required_symbols
+    MissingSymbol(String),
+    /// [`Reloadable::commit_candidate`][] was called without a candidate that
+    /// passed [`Reloadable::validate_candidate`][].
+    ///
+    /// [`Reloadable::commit_candidate`]: struct.Reloadable.html#method.commit_candidate
+    /// [`Reloadable::validate_candidate`]: struct.Reloadable.html#method.validate_candidate
+    NoCandidate,
+    /// One of the library's lifecycle functions returned an error, with the
+    /// error's message.
+    ///
+    /// See [`live_reload!`][] for how a library reports errors.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    Library(String),
+    /// The library's `update` panicked, with the panic's message. The library
+    /// caught the panic before it reached the host, and the `Reloadable` is
+    /// now poisoned, see [`Reloadable::reload_now_with`][].
+    ///
+    /// [`Reloadable::reload_now_with`]: struct.Reloadable.html#method.reload_now_with
+    Panicked(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+/// Say what was being done to which file in an I/O error, which otherwise
+/// only says what went wrong. The error keeps its kind.
+fn io_context<'a>(action: &'a str, path: &'a Path) -> impl FnOnce(std::io::Error) -> Error + 'a {
+    move |err| {
+        let message = format!("couldn't {} {}: {}", action, path.display(), err);
+        Error::Io(std::io::Error::new(err.kind(), message))
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(err: notify::Error) -> Error {
+        Error::Watch(err)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match *self {
+            Error::Io(ref err) => write!(fmt, "I/O error: {}", err),
+            Error::MissingReloadApi(ref path) => write!(
+                fmt,
+                "{} doesn't export RELOAD_API, make sure it's built with \
+                 crate-type = [\"cdylib\"] and uses live_reload!",
+                path.display()
+            ),
+            Error::NotAReloadApi(ref path) => write!(
+                fmt,
+                "{} exports a RELOAD_API that wasn't made by live_reload!",
+                path.display()
+            ),
+            Error::NotADynamicLibrary(ref path) => write!(
+                fmt,
+                "{} is an rlib, not a dynamic library, build it with \
+                 crate-type = [\"cdylib\"]",
+                path.display()
+            ),
+            Error::Watch(ref err) => {
+                write!(fmt, "error while watching the library for changes: {}", err)
+            }
+            Error::MismatchedHost => "mismatch between host and library's Host types".fmt(fmt),
+            Error::MismatchedAbi { expected, found } => write!(
+                fmt,
+                "library was built for live-reload ABI version {}, but the host uses version {}",
+                found, expected
+            ),
+            Error::QuitRequestedAtInit(code) => {
+                write!(
+                    fmt,
+                    "the library asked to quit with code {} during init",
+                    code
+                )
+            }
+            Error::NotLoaded => "no library is loaded".fmt(fmt),
+            Error::ReentrantReload => "can't reload the library while it's updating".fmt(fmt),
+            Error::StateWouldMove {
+                capacity,
+                requested,
+            } => write!(
+                fmt,
+                "the State needs {} bytes, but only {} bytes were reserved for it",
+                requested, capacity
+            ),
+            Error::StateTooLarge { max, requested } => write!(
+                fmt,
+                "the State needs {} bytes, which is more than the limit of {} bytes",
+                requested, max
+            ),
+            Error::MismatchedInput => "mismatch between host and library's input types".fmt(fmt),
+            Error::MismatchedInitArgs => {
+                "mismatch between host and library's init argument types".fmt(fmt)
+            }
+            Error::MismatchedStateSize { expected, found } => write!(
+                fmt,
+                "expected {} bytes of State, but got {} bytes",
+                expected, found
+            ),
+            Error::MissingFunction(name) => {
+                write!(fmt, "the library doesn't provide an {} function", name)
+            }
+            Error::MissingSymbol(ref name) => {
+                write!(
+                    fmt,
+                    "the library doesn't export the required symbol {}",
+                    name
+                )
+            }
+            Error::NoCandidate => "no candidate library passed validation".fmt(fmt),
+            Error::Library(ref message) => {
+                write!(fmt, "the library reported an error: {}", message)
+            }
+            Error::Panicked(ref message) => write!(fmt, "the library panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::Watch(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<Host> AppSym<Host> {
+    fn new<P: AsRef<Path>>(path: P, strategy: &LoadingStrategy) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let library = match open_library(path, strategy) {
+            Ok(library) => library,
+            Err(_) if is_rlib(path) => return Err(Error::NotADynamicLibrary(path.to_owned())),
+            Err(err) => return Err(io_context("load", path)(err)),
+        };
+        let api = match unsafe { library.get::<*const u64>(b"RELOAD_API") } {
+            Ok(sym) => *sym,
+            Err(_) => return Err(Error::MissingReloadApi(path.to_owned())),
+        };
+        // Nothing else in the symbol can be trusted until this matches
+        let api = match unsafe { *api } {
+            internals::MAGIC => Api::Rust(api as *const internals::ReloadApi<Host>),
+            internals::MAGIC_C => Api::C(api as *const internals::ReloadApiC<Host>),
+            _ if strategy.skip_magic_check => Api::Rust(api as *const internals::ReloadApi<Host>),
+            _ => return Err(Error::NotAReloadApi(path.to_owned())),
+        };
+        for name in &strategy.required_symbols {
+            if unsafe { library.get::<*const ()>(name.as_bytes()) }.is_err() {
+                return Err(Error::MissingSymbol(name.clone()));
+            }
+        }
+        Ok(AppSym {
+            _lib: ManuallyDrop::new(library),
+            api,
+            path: path.to_owned(),
+            fd_file: None,
+            info: PluginInfo::default(),
+            hash: None,
+            leak: false,
+        })
+    }
+
+    /// Record that the library was loaded through `/proc/self/fd` from `file`,
+    /// which is the library at `path` or a copy of it, see `open_library_fd`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn loaded_through(mut self, file: std::fs::File, path: &Path) -> Self {
+        self.path = path.to_owned();
+        self.fd_file = Some(file);
+        self
+    }
+
+    /// Get the library's `ReloadApi`, unless it uses the C ABI.
+    ///
+    /// Only the Rust ABI has the optional functions, so this is how to get at
+    /// them.
+    fn rust_api(&self) -> Option<&internals::ReloadApi<Host>> {
+        match self.api {
+            Api::Rust(api) => Some(unsafe { &*api }),
+            Api::C(_) => None,
+        }
+    }
+
+    /// The address of the library's `RELOAD_API`.
+    fn address(&self) -> usize {
+        match self.api {
+            Api::Rust(api) => api as usize,
+            Api::C(api) => api as usize,
+        }
+    }
+
+    fn abi_version(&self) -> u32 {
+        match self.api {
+            Api::Rust(api) => unsafe { &*api }.abi_version,
+            Api::C(api) => unsafe { &*api }.abi_version,
+        }
+    }
+
+    /// The name of the library's package.
+    fn name(&self) -> &str {
+        match self.api {
+            Api::Rust(api) => unsafe { &*api }.name,
+            Api::C(api) => {
+                let name = unsafe { &*api }.name;
+                if name.is_null() {
+                    return "";
+                }
+                let name = unsafe { std::ffi::CStr::from_ptr(name) };
+                name.to_str().unwrap_or("")
+            }
+        }
+    }
+
+    /// The size of the library's `State`.
+    fn state_size(&self) -> usize {
+        match self.api {
+            Api::Rust(api) => (unsafe { &*api }.size)(),
+            Api::C(api) => unsafe { &*api }.state_size,
+        }
+    }
+
+    fn state_version(&self) -> u32 {
+        self.rust_api().map_or(0, |api| api.state_version)
+    }
+
+    fn built_at(&self) -> Option<u64> {
+        self.rust_api()
+            .map(|api| api.built_at)
+            .filter(|&built_at| built_at != 0)
+    }
+
+    fn init_args_size(&self) -> usize {
+        match self.api {
+            Api::Rust(api) => unsafe { &*api }.init_args_size,
+            Api::C(api) => unsafe { &*api }.init_args_size,
+        }
+    }
+
+    fn input_size(&self) -> usize {
+        match self.api {
+            Api::Rust(api) => unsafe { &*api }.input_size,
+            Api::C(api) => unsafe { &*api }.input_size,
+        }
+    }
+
+    fn host_layout(&self) -> (usize, usize) {
+        match self.api {
+            Api::Rust(api) => (unsafe { &*api }.host_size, unsafe { &*api }.host_align),
+            Api::C(api) => (unsafe { &*api }.host_size, unsafe { &*api }.host_align),
+        }
+    }
+
+    fn init(
+        &self,
+        host: &mut Host,
+        state: *mut (),
+        args: *const (),
+        error: &mut Option<String>,
+    ) -> ShouldQuit {
+        match self.api {
+            Api::Rust(api) => (unsafe { &*api }.init)(host, state, args, &self.info, error),
+            Api::C(api) => {
+                let api = unsafe { &*api };
+                let quit = (api.init)(host, state as *mut _, args as *const _);
+                take_c_error(api, error);
+                quit
+            }
+        }
+    }
+
+    fn reload(&self, host: &mut Host, state: *mut (), error: &mut Option<String>) {
+        match self.api {
+            Api::Rust(api) => (unsafe { &*api }.reload)(host, state, &self.info, error),
+            Api::C(api) => {
+                let api = unsafe { &*api };
+                (api.reload)(host, state as *mut _);
+                take_c_error(api, error);
+            }
+        }
+    }
+
+    fn update(
+        &self,
+        host: &mut Host,
+        state: *mut (),
+        input: *const (),
+        input_len: usize,
+        error: &mut Option<String>,
+    ) -> ShouldQuit {
+        match self.api {
+            Api::Rust(api) => {
+                (unsafe { &*api }.update)(host, state, input, input_len, &self.info, error)
+            }
+            Api::C(api) => {
+                let api = unsafe { &*api };
+                let quit = (api.update)(host, state as *mut _, input as *const _, input_len);
+                take_c_error(api, error);
+                quit
+            }
+        }
+    }
+
+    fn unload(&self, host: &mut Host, state: *mut (), error: &mut Option<String>) {
+        match self.api {
+            Api::Rust(api) => (unsafe { &*api }.unload)(host, state, error),
+            Api::C(api) => {
+                let api = unsafe { &*api };
+                (api.unload)(host, state as *mut _);
+                take_c_error(api, error);
+            }
+        }
+    }
+
+    fn deinit(&self, host: &mut Host, state: *mut (), error: &mut Option<String>) {
+        match self.api {
+            Api::Rust(api) => (unsafe { &*api }.deinit)(host, state, error),
+            Api::C(api) => {
+                let api = unsafe { &*api };
+                (api.deinit)(host, state as *mut _);
+                take_c_error(api, error);
+            }
+        }
+    }
+
+    /// The library's `on_state_moved` function, if it has one.
+    fn state_moved(&self) -> Option<fn(&mut Host, *mut (), usize, usize)> {
+        self.rust_api()?.state_moved
+    }
+
+    /// The number of allocations the library made so far, see
+    /// `internals::allocations`.
+    #[cfg(feature = "count-allocations")]
+    fn allocations(&self) -> usize {
+        self.rust_api().map_or(0, |api| (api.allocations)())
+    }
+
+    /// The requests the library made with `request_reload` since they were
+    /// last taken.
+    fn take_reload_requests(&self) -> ReloadRequest {
+        ReloadRequest(
+            self.rust_api()
+                .map_or(0, |api| (api.take_reload_requests)()),
+        )
+    }
+
+    /// Check that the library was built against a compatible version of
+    /// `live-reload` and a compatible `Host` type.
+    ///
+    /// Types can't be compared by identity across separately compiled
+    /// binaries, so this compares the size and alignment the library saw for
+    /// `Host` with the ones the host program sees.
+    fn verify(&self) -> Result<(), Error> {
+        let expected = match self.api {
+            Api::Rust(_) => internals::ABI_VERSION,
+            Api::C(_) => internals::ABI_VERSION_C,
+        };
+        if self.abi_version() != expected {
+            return Err(Error::MismatchedAbi {
+                expected,
+                found: self.abi_version(),
+            });
+        }
+        if self.host_layout() != (std::mem::size_of::<Host>(), std::mem::align_of::<Host>()) {
+            return Err(Error::MismatchedHost);
+        }
+        Ok(())
+    }
+}
+
+/// Move the error that a library using the C protocol reported from its last
+/// lifecycle function into `error`, see `ReloadApiC::take_error`.
+fn take_c_error<Host>(api: &internals::ReloadApiC<Host>, error: &mut Option<String>) {
+    let message = match api.take_error {
+        Some(take_error) => take_error(),
+        None => return,
+    };
+    if !message.is_null() {
+        let message = unsafe { std::ffi::CStr::from_ptr(message) };
+        *error = Some(message.to_string_lossy().into_owned());
+    }
+}
+
+/// Check whether a file is an `ar` archive, which is what rlibs are.
+fn is_rlib(path: &Path) -> bool {
+    use std::io::Read;
+    let mut magic = [0; 8];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok()
+        && &magic == b"!<arch>\n"
+}
+
+/// Check whether two canonical paths name the same file.
+///
+/// The default filesystems on macOS and Windows are case-insensitive, and the
+/// paths reported by the watcher don't always have the same case as the
+/// canonical path, so the comparison ignores case there.
+#[cfg(any(target_os = "macos", target_os = "ios", windows))]
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b || a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+}
+
+/// Check whether two canonical paths name the same file.
+#[cfg(not(any(target_os = "macos", target_os = "ios", windows)))]
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b
+}
+
+/// Format `bytes` like `hexdump -C`, see `Reloadable::dump_state`.
+fn hex_dump(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    let mut dump = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(dump, "{:08x} ", line * 16);
+        for i in 0..16 {
+            if i % 8 == 0 {
+                dump.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(dump, "{:02x} ", byte);
+                }
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push_str(" |");
+        for &byte in chunk {
+            let printable = byte.is_ascii_graphic() || byte == b' ';
+            dump.push(if printable { byte as char } else { '.' });
+        }
+        dump.push_str("|\n");
+    }
+    let _ = writeln!(dump, "{:08x}", bytes.len());
+    dump
+}
+
+/// Hash the contents of a library file, to tell whether a rebuild changed it.
+fn hash_library(path: &Path) -> std::io::Result<u64> {
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hasher.write(&std::fs::read(path)?);
+    Ok(hasher.finish())
+}
+
+/// Load the library at `path` from a copy in the live slot `slot` next to
+/// it, since the loader would hand back a library that's still open.
+fn load_live_copy<Host>(
+    path: &Path,
+    slot: usize,
+    strategy: &LoadingStrategy,
+) -> Result<AppSym<Host>, Error> {
+    let extension = format!("{}.live.{}", slot, std::env::consts::DLL_EXTENSION);
+    let live_path = strategy.live_path(path, &extension);
+    strategy.retry_load(|| {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            if strategy.map_original {
+                if let Ok((file, fd_path)) = open_library_fd(path) {
+                    return AppSym::new(fd_path, strategy)
+                        .map(|sym| sym.loaded_through(file, path));
+                }
+            }
+        }
+        copy_library(path, &live_path, strategy)?;
+        let sym = AppSym::new(&live_path, strategy);
+        // The loaded library stays open after its file is gone, except on Windows
+        if cfg!(unix) {
+            let _ = std::fs::remove_file(&live_path);
+        }
+        sym
+    })
+}
+
+/// Copy a library to the path it gets loaded from.
+fn copy_library(path: &Path, live_path: &Path, strategy: &LoadingStrategy) -> Result<(), Error> {
+    // Every now and then it seems that the unloading process keeps the dll locked, or
+    // another process spies on it, or the source dll is current being written.
+    // In these cases, we retry a few times before giving up.
+    let mut attempt = 1;
+    'retry: loop {
+        match std::fs::copy(path, live_path) {
+            Result::Err(io_err)
+                if cfg!(windows)
+                    && io_err.raw_os_error() == Some(32)
+                    && attempt <= strategy.max_retries =>
+            {
+                std::thread::sleep(strategy.retry_delay);
+                attempt += 1;
+                continue 'retry;
+            }
+            Result::Err(io_err) => {
+                return Err(io_context("copy the library to", live_path)(io_err))
+            }
+            Result::Ok(_) => break 'retry,
+        }
+    }
+    #[cfg(windows)]
+    copy_pdb(path, live_path);
+    Ok(())
+}
+
+/// Open a library so that it can be loaded without copying it, and get the
+/// path in `/proc/self/fd` to load it from, see
+/// `LoadingStrategy::map_original`.
+///
+/// The `File` has to stay open for as long as the library is loaded, since the
+/// loader hands back an open library that has the same path, and the number of
+/// a closed file gets reused. The loader also hands back an open library that
+/// was loaded from the same file, so in that case this falls back to a copy in
+/// an in-memory file.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn open_library_fd(path: &Path) -> std::io::Result<(std::fs::File, PathBuf)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+
+    let fd_dir = Path::new("/proc/self/fd");
+    if !fd_dir.is_dir() {
+        return Err(std::io::ErrorKind::NotFound.into());
+    }
+    let file = std::fs::File::open(path)?;
+    let fd_path = fd_dir.join(file.as_raw_fd().to_string());
+    let name = CString::new(fd_path.as_os_str().as_bytes())?;
+    // This only finds a library that's already loaded, without loading it
+    let open = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_LAZY | libc::RTLD_NOLOAD) };
+    if !open.is_null() {
+        unsafe { libc::dlclose(open) };
+        return copy_library_to_memory(path);
+    }
+    Ok((file, fd_path))
+}
+
+/// Copy a library into an in-memory file, which can be loaded from the returned
+/// path for as long as the `File` is open.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn copy_library_to_memory(path: &Path) -> std::io::Result<(std::fs::File, PathBuf)> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let fd_dir = Path::new("/proc/self/fd");
+    // The name shows up in /proc/self/maps, so debuggers can tell what it is
+    let name = path.file_name().unwrap_or_default().as_bytes().to_vec();
+    let name = CString::new(name).map_err(std::io::Error::from)?;
+    let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut copy = unsafe { std::fs::File::from_raw_fd(fd) };
+    std::io::copy(&mut std::fs::File::open(path)?, &mut copy)?;
+    let copy_path = fd_dir.join(copy.as_raw_fd().to_string());
+    Ok((copy, copy_path))
+}
+
+/// Only run the current thread on the CPU core numbered `core`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn pin_to_core(core: usize) -> std::io::Result<()> {
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("there's no CPU core {}", core),
+        ));
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Thread affinity isn't supported on this platform, so this does nothing.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn pin_to_core(_: usize) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Copy the library's PDB next to its loaded copy, so that debuggers can still
+/// find the symbols for the code that's actually loaded.
+///
+/// The library records the path of its PDB, so the copy is patched to point at
+/// the copied PDB. This only works when the new path isn't longer than the
+/// original one, which holds for Cargo's usual `deps\name.pdb` path since
+/// `name.live.pdb` has the same length. This is best-effort, failing to copy
+/// the debug info shouldn't prevent loading the library.
+#[cfg(windows)]
+fn copy_pdb(path: &Path, live_path: &Path) {
+    let pdb = path.with_extension("pdb");
+    let live_pdb = live_path.with_extension("pdb");
+    if std::fs::copy(&pdb, &live_pdb).is_err() {
+        return;
+    }
+    let live_pdb = match std::env::current_dir() {
+        Ok(dir) => dir.join(live_pdb),
+        Err(_) => return,
+    };
+    let live_pdb = live_pdb.to_string_lossy();
+    // Debuggers don't understand verbatim paths, which canonicalize produces
+    let live_pdb = live_pdb.trim_start_matches(r"\\?\");
+    let _ = patch_pdb_path(live_path, live_pdb.as_bytes());
+}
+
+/// Replace the PDB path in the CodeView debug records of a library.
+#[cfg(windows)]
+fn patch_pdb_path(library: &Path, pdb: &[u8]) -> std::io::Result<()> {
+    // An RSDS record is the signature, a 16 byte GUID, a 4 byte age, and a
+    // null-terminated path
+    const PATH_OFFSET: usize = 4 + 16 + 4;
+    let mut bytes = std::fs::read(library)?;
+    let records: Vec<usize> = bytes
+        .windows(4)
+        .enumerate()
+        .filter(|&(_, window)| window == b"RSDS")
+        .map(|(i, _)| i + PATH_OFFSET)
+        .collect();
+    let mut patched = false;
+    for start in records {
+        let len = match bytes
+            .get(start..)
+            .and_then(|rest| rest.iter().position(|&b| b == 0))
+        {
+            Some(len) => len,
+            None => continue,
+        };
+        let old = bytes[start..start + len].to_ascii_lowercase();
+        if !old.ends_with(b".pdb") || pdb.len() > len {
+            continue;
+        }
+        bytes[start..start + pdb.len()].copy_from_slice(pdb);
+        for byte in &mut bytes[start + pdb.len()..start + len] {
+            *byte = 0;
+        }
+        patched = true;
+    }
+    if patched {
+        std::fs::write(library, bytes)?;
+    }
+    Ok(())
+}
+
+/// The default for [`Builder::max_state_size`][].
+///
+/// [`Builder::max_state_size`]: struct.Builder.html#method.max_state_size
+const DEFAULT_MAX_STATE_SIZE: usize = 1 << 30;
+
+/// The most times [`Reloadable::reload`][] reloads the library in one call.
+///
+/// [`Reloadable::reload`]: struct.Reloadable.html#method.reload
+const MAX_RELOADS_PER_CALL: usize = 3;
+
+/// The requests made with [`request_reload`][] in the binary this is
+/// compiled into, that the host hasn't picked up yet.
+///
+/// [`request_reload`]: fn.request_reload.html
+static RELOAD_REQUESTS: AtomicU32 = AtomicU32::new(0);
+
+/// Ask the host to do something, like reading the library's assets again.
+///
+/// This is meant to be called from a reloadable library, typically from its
+/// `update` when it notices that its data files changed. It doesn't stop
+/// `update`: the request is recorded, and the host picks it up with
+/// [`Reloadable::take_reload_requests`][] after `update` returns. Asking
+/// several times before the host picks the requests up is the same as asking
+/// once.
+///
+/// ```rust,no_run
+/// use live_reload::{request_reload, ReloadRequest};
+///
+/// // In the library:
+/// request_reload(ReloadRequest::ASSETS);
+///
+/// // In the host, after update:
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # let mut app = live_reload::Reloadable::new("target/debug/libgame.so", Host).unwrap();
+/// if app.take_reload_requests().contains(ReloadRequest::ASSETS) {
+///     // Read the assets again
+/// }
+/// ```
+///
+/// [`Reloadable::take_reload_requests`]: struct.Reloadable.html#method.take_reload_requests
+pub fn request_reload(request: ReloadRequest) {
+    RELOAD_REQUESTS.fetch_or(request.0, Ordering::SeqCst);
+}
+
+/// Record the build time of a reloadable library, from its build script.
+///
+/// Call this from the library's `build.rs`, with `live-reload` as a build
+/// dependency, and [`live_reload!`][] puts the time into the library so that
+/// the host can read it with [`Reloadable::plugin_built_at`][]. Without
+/// `rerun-if-changed` lines, Cargo runs the build script again whenever a
+/// file in the package changes, so the time stays current.
+///
+/// ```rust,no_run
+/// // build.rs
+/// fn main() {
+///     live_reload::emit_built_at();
+/// }
+/// ```
+///
+/// [`live_reload!`]: macro.live_reload.html
+/// [`Reloadable::plugin_built_at`]: struct.Reloadable.html#method.plugin_built_at
+pub fn emit_built_at() {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    println!("cargo:rustc-env=LIVE_RELOAD_BUILT_AT={}", now);
+}
+
+/// What the host tells a library about where it was loaded from.
+///
+/// A library gets this as the last parameter of its `init`, `reload` and
+/// `update` when it asks for it with `info: PluginInfo;` in [`live_reload!`][].
+///
+/// [`live_reload!`]: macro.live_reload.html
+#[derive(Clone, Debug, Default)]
+pub struct PluginInfo {
+    dir: Option<PathBuf>,
+}
+
+impl PluginInfo {
+    /// The info for a library loaded from `library`.
+    fn for_library(library: &Path) -> Self {
+        PluginInfo {
+            dir: library.parent().map(Path::to_owned),
+        }
+    }
+
+    /// Get the directory containing the library file that this code was
+    /// loaded from.
+    ///
+    /// This is for finding resources that are kept next to the library,
+    /// without depending on the working directory of the host program. It's
+    /// the directory of the library's real path, even when the host loads the
+    /// library from a copy.
+    ///
+    /// For a library that's linked in with [`StaticReloadable`][], this is
+    /// the directory of the executable, if it's known.
+    ///
+    /// [`StaticReloadable`]: struct.StaticReloadable.html
+    pub fn plugin_dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
+
+/// The number of `SIGUSR1`s received since the handler was installed.
+#[cfg(unix)]
+static SIGUSR1_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The `SIGUSR1` handler installed by `Reloadable::reload_on_sigusr1`.
+///
+/// This must stay async-signal-safe, so it only bumps an atomic counter.
+#[cfg(unix)]
+extern "C" fn on_sigusr1(_signal: libc::c_int) {
+    SIGUSR1_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Find the address range of the loaded library containing `addr`, from the
+/// program headers of each loaded object.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn library_region(addr: usize) -> Option<(usize, usize)> {
+    unsafe extern "C" fn find(
+        info: *mut libc::dl_phdr_info,
+        _size: libc::size_t,
+        data: *mut libc::c_void,
+    ) -> libc::c_int {
+        let (addr, region) = &mut *(data as *mut (usize, Option<(usize, usize)>));
+        let info = &*info;
+        if info.dlpi_phdr.is_null() {
+            return 0;
+        }
+        let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+        let (mut start, mut end) = (usize::MAX, 0);
+        for phdr in phdrs.iter().filter(|phdr| phdr.p_type == libc::PT_LOAD) {
+            let segment = (info.dlpi_addr as usize).wrapping_add(phdr.p_vaddr as usize);
+            start = start.min(segment);
+            end = end.max(segment + phdr.p_memsz as usize);
+        }
+        if start <= *addr && *addr < end {
+            *region = Some((start, end));
+            1
+        } else {
+            0
+        }
+    }
+    let mut data = (addr, None);
+    unsafe { libc::dl_iterate_phdr(Some(find), &mut data as *mut _ as *mut libc::c_void) };
+    data.1
+}
+
+/// Find the address range of the loaded library containing `addr`, from the
+/// segments in its Mach-O header.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn library_region(addr: usize) -> Option<(usize, usize)> {
+    const MH_MAGIC_64: u32 = 0xfeed_facf;
+    const LC_SEGMENT_64: u32 = 0x19;
+    unsafe fn read<T>(ptr: *const u8) -> T {
+        std::ptr::read_unaligned(ptr as *const T)
+    }
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    if unsafe { libc::dladdr(addr as *const libc::c_void, &mut info) } == 0 {
+        return None;
+    }
+    let header = info.dli_fbase as *const u8;
+    if header.is_null() || unsafe { read::<u32>(header) } != MH_MAGIC_64 {
+        return None;
+    }
+    let ncmds = unsafe { read::<u32>(header.add(16)) };
+    // The load commands follow the 32 byte mach_header_64
+    let mut command = unsafe { header.add(32) };
+    let (mut text, mut start, mut end) = (None, u64::MAX, 0);
+    for _ in 0..ncmds {
+        let (kind, size) = unsafe { (read::<u32>(command), read::<u32>(command.add(4))) };
+        if kind == LC_SEGMENT_64 {
+            let name: [u8; 16] = unsafe { read(command.add(8)) };
+            let (vmaddr, vmsize) =
+                unsafe { (read::<u64>(command.add(24)), read::<u64>(command.add(32))) };
+            if name.starts_with(b"__TEXT\0") {
+                text = Some(vmaddr);
+            }
+            if vmsize > 0 && !name.starts_with(b"__PAGEZERO\0") {
+                start = start.min(vmaddr);
+                end = end.max(vmaddr + vmsize);
+            }
+        }
+        command = unsafe { command.add(size as usize) };
+    }
+    // The header is mapped at the start of __TEXT, which gives the slide
+    let slide = (header as u64).wrapping_sub(text?);
+    Some((
+        start.wrapping_add(slide) as usize,
+        end.wrapping_add(slide) as usize,
+    ))
+}
+
+/// Find the address range of the loaded module containing `addr`.
+#[cfg(windows)]
+fn library_region(addr: usize) -> Option<(usize, usize)> {
+    use std::os::raw::c_void;
+    const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: u32 = 0x2;
+    const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS: u32 = 0x4;
+    #[repr(C)]
+    struct ModuleInfo {
+        base: *mut c_void,
+        size: u32,
+        entry: *mut c_void,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetModuleHandleExW(flags: u32, name: *const u16, module: *mut *mut c_void) -> i32;
+        fn GetCurrentProcess() -> *mut c_void;
+        fn K32GetModuleInformation(
+            process: *mut c_void,
+            module: *mut c_void,
+            info: *mut ModuleInfo,
+            size: u32,
+        ) -> i32;
+    }
+    let flags =
+        GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT;
+    let mut module = std::ptr::null_mut();
+    if unsafe { GetModuleHandleExW(flags, addr as *const u16, &mut module) } == 0 {
+        return None;
+    }
+    let mut info = ModuleInfo {
+        base: std::ptr::null_mut(),
+        size: 0,
+        entry: std::ptr::null_mut(),
+    };
+    let size = std::mem::size_of::<ModuleInfo>() as u32;
+    if unsafe { K32GetModuleInformation(GetCurrentProcess(), module, &mut info, size) } == 0 {
+        return None;
+    }
+    let start = info.base as usize;
+    Some((start, start + info.size as usize))
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    windows
+)))]
+fn library_region(_addr: usize) -> Option<(usize, usize)> {
+    None
+}
+
+/// A global allocator that counts the allocations made on each thread, for
+/// [`Reloadable::set_allocation_check`][].
+///
+/// A library crate can't pick the global allocator for the program, so the
+/// host and each library that should be counted install this themselves,
+/// wrapping the allocator they'd use otherwise. The counts can then be read
+/// with [`internals::allocations`][]. A binary that doesn't install it always
+/// counts 0.
+///
+/// ```rust
+/// #[global_allocator]
+/// static ALLOCATOR: live_reload::CountingAllocator<std::alloc::System> =
+///     live_reload::CountingAllocator::new(std::alloc::System);
+///
+/// let before = live_reload::internals::allocations();
+/// let boxed = Box::new(1);
+/// assert!(live_reload::internals::allocations() > before);
+/// # drop(boxed);
+/// ```
+///
+/// This needs the `count-allocations` feature.
+///
+/// [`Reloadable::set_allocation_check`]: struct.Reloadable.html#method.set_allocation_check
+/// [`internals::allocations`]: internals/fn.allocations.html
+#[cfg(feature = "count-allocations")]
+#[derive(Debug, Default)]
+pub struct CountingAllocator<A> {
+    inner: A,
+}
+
+#[cfg(feature = "count-allocations")]
+impl<A> CountingAllocator<A> {
+    /// Count the allocations made through `inner`.
+    pub const fn new(inner: A) -> Self {
+        CountingAllocator { inner }
+    }
+}
+
+#[cfg(feature = "count-allocations")]
+std::thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Count one allocation on the current thread.
+#[cfg(feature = "count-allocations")]
+fn count_allocation() {
+    // The thread local is gone while the thread is shutting down
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+#[cfg(feature = "count-allocations")]
+unsafe impl<A: std::alloc::GlobalAlloc> std::alloc::GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        count_allocation();
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        count_allocation();
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, size: usize) -> *mut u8 {
+        count_allocation();
+        self.inner.realloc(ptr, layout, size)
+    }
+}
+
+/// Get the platform-specific file name of a `cdylib` crate.
+///
+/// Cargo names the dynamic library built from a crate with
+/// `crate-type = ["cdylib"]` differently on each platform, for example
+/// `libgame.so` on Linux, `libgame.dylib` on macOS, and `game.dll` on Windows.
+/// This gives the name for the current platform, so that the host can find the
+/// library without hardcoding it.
+///
+/// ```rust
+/// use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+///
+/// assert_eq!(
+///     live_reload::library_file_name("game"),
+///     format!("{}game{}", DLL_PREFIX, DLL_SUFFIX),
+/// );
+/// ```
+pub fn library_file_name(name: &str) -> String {
+    use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+    format!("{}{}{}", DLL_PREFIX, name, DLL_SUFFIX)
+}
+
+/// A Cargo build profile, see [`Reloadable::switch_profile`][].
+///
+/// [`Reloadable::switch_profile`]: struct.Reloadable.html#method.switch_profile
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Profile {
+    /// The `dev` profile, built into `target/debug`.
+    Debug,
+    /// The `release` profile, built into `target/release`.
+    Release,
+}
+
+impl Profile {
+    /// The name of the directory Cargo builds this profile into.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+        }
+    }
+
+    /// Get the profile that Cargo builds into `dir`, if any.
+    fn of_dir(dir: &Path) -> Option<Profile> {
+        match dir.file_name()?.to_str()? {
+            "debug" => Some(Profile::Debug),
+            "release" => Some(Profile::Release),
+            _ => None,
+        }
+    }
+}
+
+/// A bump allocator for memory that only lives for one frame.
+///
+/// Reloadable libraries can't keep global state, so they can't keep their own
+/// allocator for temporary memory either. Instead, the host can put a
+/// `ScratchArena` in its `Host` struct for the library to allocate from, and
+/// reset it at the start of every update with
+/// [`Reloadable::set_before_update`][]. The memory belongs to the host, so it
+/// survives reloads, and the library never has to free anything.
+///
+/// Only `Copy` types can be allocated, since nothing is dropped on reset.
+///
+/// ```rust
+/// use live_reload::ScratchArena;
+///
+/// let mut arena = ScratchArena::new(1024);
+/// let numbers = arena.alloc_slice(4, 0u32).unwrap();
+/// numbers[2] = 7;
+/// assert_eq!(arena.used(), 16);
+/// arena.reset();
+/// assert_eq!(arena.used(), 0);
+/// ```
+///
+/// [`Reloadable::set_before_update`]: struct.Reloadable.html#method.set_before_update
+pub struct ScratchArena {
+    buffer: Box<[std::cell::UnsafeCell<u64>]>,
+    used: std::cell::Cell<usize>,
+}
+
+impl ScratchArena {
+    /// Create an arena that can hold `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        let words = capacity.div_ceil(std::mem::size_of::<u64>());
+        ScratchArena {
+            buffer: (0..words).map(|_| std::cell::UnsafeCell::new(0)).collect(),
+            used: std::cell::Cell::new(0),
+        }
+    }
+
+    /// Move `value` into the arena.
+    ///
+    /// This returns `None` if the arena doesn't have enough space left.
+    pub fn alloc<T: Copy>(&self, value: T) -> Option<&mut T> {
+        self.alloc_slice(1, value).map(|slice| &mut slice[0])
+    }
+
+    /// Allocate a slice of `len` copies of `value` in the arena.
+    ///
+    /// This returns `None` if the arena doesn't have enough space left.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice<T: Copy>(&self, len: usize, value: T) -> Option<&mut [T]> {
+        let base = self.buffer.as_ptr() as usize;
+        let align = std::mem::align_of::<T>();
+        let start = (base + self.used.get()).checked_add(align - 1)? & !(align - 1);
+        let end = start.checked_add(std::mem::size_of::<T>().checked_mul(len)?)?;
+        if end > base + self.capacity() {
+            return None;
+        }
+        self.used.set(end - base);
+        // Every allocation is a separate part of the buffer, and the buffer
+        // can only be reused after a reset, which needs the arena mutably
+        unsafe {
+            let ptr = (self.buffer.as_ptr() as *mut u8).add(start - base) as *mut T;
+            for i in 0..len {
+                ptr.add(i).write(value);
+            }
+            Some(std::slice::from_raw_parts_mut(ptr, len))
+        }
+    }
+
+    /// The number of bytes allocated since the last reset, including padding.
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    /// The number of bytes the arena can hold.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len() * std::mem::size_of::<u64>()
+    }
+
+    /// Free everything allocated in the arena.
+    pub fn reset(&mut self) {
+        self.used.set(0);
+    }
+}
+
+impl<Host> Builder<Host> {
+    /// Start configuring a new Reloadable library at `path`.
+    pub fn new<P: AsRef<Path>>(path: P, host: Host) -> Self {
+        Builder {
+            path: path.as_ref().to_owned(),
+            host,
+            verify: true,
+            trusting: false,
+            stable_state: None,
+            reserve_state: 0,
+            max_state_size: DEFAULT_MAX_STATE_SIZE,
+            state_alignment: Default::default(),
+            guard_state: false,
+            skip_init: false,
+            reload_on_initial_load: false,
+            loading_strategy: Default::default(),
+            events: None,
+            watch_mode: WatchMode::Auto,
+            #[cfg(unix)]
+            mmap_state: None,
+        }
+    }
+
+    /// Use a different source of filesystem events instead of watching the
+    /// library's directory.
+    ///
+    /// See [`ReloadEvents`][] for details.
+    ///
+    /// [`ReloadEvents`]: trait.ReloadEvents.html
+    pub fn events<E: ReloadEvents + Send + 'static>(mut self, events: E) -> Self {
+        self.events = Some(Box::new(events));
+        self
+    }
+
+    /// Choose how the library's directory is watched for changes.
+    ///
+    /// The default, [`WatchMode::Auto`][], polls directories on network and
+    /// FUSE filesystems and falls back to polling if the native watcher
+    /// doesn't work, so this only needs changing to force one or the other.
+    /// It has no effect if a different event source is given to
+    /// [`events`][].
+    ///
+    /// [`WatchMode::Auto`]: enum.WatchMode.html#variant.Auto
+    /// [`events`]: struct.Builder.html#method.events
+    pub fn watch_mode(mut self, mode: WatchMode) -> Self {
+        self.watch_mode = mode;
+        self
+    }
+
+    /// Put the copies of the library that get loaded in `dir`, instead of next
+    /// to the library.
+    ///
+    /// The library is loaded from a copy on Windows, with
+    /// [`LoadingStrategy::load_new_first`][], and in a few other cases, and
+    /// by default the copy is made in the library's directory. This is for
+    /// setups where that directory can't be written to, like a read-only
+    /// deployment. Building fails with [`Error::Io`][] if a file can't be
+    /// created in `dir`. Two `Reloadable`s for libraries with the same file
+    /// name shouldn't share a directory.
+    ///
+    /// This sets [`LoadingStrategy::copy_dir`][].
+    ///
+    /// [`LoadingStrategy::load_new_first`]: struct.LoadingStrategy.html#structfield.load_new_first
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`LoadingStrategy::copy_dir`]: struct.LoadingStrategy.html#structfield.copy_dir
+    pub fn copy_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.loading_strategy.copy_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Set the [`LoadingStrategy`][] used to load the library.
+    ///
+    /// The initial load never retries, but otherwise uses this strategy. It
+    /// can be changed later through [`Reloadable::loading_strategy`][].
+    ///
+    /// [`LoadingStrategy`]: struct.LoadingStrategy.html
+    /// [`Reloadable::loading_strategy`]: struct.Reloadable.html#structfield.loading_strategy
+    pub fn loading_strategy(mut self, strategy: LoadingStrategy) -> Self {
+        self.loading_strategy = strategy;
+        self
+    }
+
+    /// Retry loading the library up to `retries` times when it fails to open,
+    /// waiting `delay` before the first retry and twice as long before each
+    /// one after that.
+    ///
+    /// This sets [`LoadingStrategy::load_retries`][] and
+    /// [`LoadingStrategy::load_retry_delay`][].
+    ///
+    /// [`LoadingStrategy::load_retries`]: struct.LoadingStrategy.html#structfield.load_retries
+    /// [`LoadingStrategy::load_retry_delay`]: struct.LoadingStrategy.html#structfield.load_retry_delay
+    pub fn load_retries(mut self, retries: usize, delay: Duration) -> Self {
+        self.loading_strategy.load_retries = retries;
+        self.loading_strategy.load_retry_delay = delay;
+        self
+    }
+
+    /// Choose whether the library's `Host` type is checked each time it's
+    /// loaded. This is true by default, see [`Reloadable::new_unchecked`][]
+    /// for the tradeoff.
+    ///
+    /// [`Reloadable::new_unchecked`]: struct.Reloadable.html#method.new_unchecked
+    pub fn check_host(mut self, check: bool) -> Self {
+        self.verify = check;
+        self
+    }
+
+    /// Skip every check on the library that can be skipped, for a trusted
+    /// setup where the host and library are always rebuilt together. False by
+    /// default, see [`Reloadable::new_trusting`][] for what's skipped.
+    ///
+    /// Passing true also turns off [`check_host`][] and sets
+    /// [`LoadingStrategy::skip_magic_check`][].
+    ///
+    /// [`Reloadable::new_trusting`]: struct.Reloadable.html#method.new_trusting
+    /// [`check_host`]: struct.Builder.html#method.check_host
+    /// [`LoadingStrategy::skip_magic_check`]: struct.LoadingStrategy.html#structfield.skip_magic_check
+    pub fn trusting(mut self, trust: bool) -> Self {
+        self.trusting = trust;
+        self.verify = !trust;
+        self.loading_strategy.skip_magic_check = trust;
+        self
+    }
+
+    /// Keep the `State` buffer at a fixed address for the whole run.
+    ///
+    /// Normally, when a reload makes the `State` bigger than the buffer's
+    /// capacity, the buffer is moved to a new allocation, which breaks any
+    /// pointers into the state that the library stored in it. This allocates
+    /// room for up to `max_size` bytes up front, and then refuses to move the
+    /// buffer: a reload that needs more than that fails with
+    /// [`Error::StateWouldMove`][] instead.
+    ///
+    /// [`Error::StateWouldMove`]: enum.Error.html#variant.StateWouldMove
+    pub fn stable_state(mut self, max_size: usize) -> Self {
+        self.stable_state = Some(max_size);
+        self
+    }
+
+    /// Preallocate the `State` buffer to fit a state of up to `max_size`
+    /// bytes before the library is first loaded.
+    ///
+    /// Unlike [`stable_state`][], the buffer can still grow past this later.
+    /// See [`Reloadable::reserve_state`][].
+    ///
+    /// [`stable_state`]: struct.Builder.html#method.stable_state
+    /// [`Reloadable::reserve_state`]: struct.Reloadable.html#method.reserve_state
+    pub fn reserve_state(mut self, max_size: usize) -> Self {
+        self.reserve_state = max_size;
+        self
+    }
+
+    /// Keep the `State` in the file at `path`, so that it survives restarting
+    /// the host as well as reloads.
+    ///
+    /// The file is mapped into memory and the library works on it directly,
+    /// so every change to the state ends up in the file without having to be
+    /// saved. It's created if it doesn't exist. If it does, and it holds a
+    /// state that the library's `state_version` and `State` size match, that
+    /// state is picked up again: instead of `init`, the library's `reload` is
+    /// called on it, just like after a reload. Otherwise it's zeroed and
+    /// initialized as usual. A file that doesn't hold a state at all fails
+    /// with [`Error::Io`][], instead of being overwritten. This only happens
+    /// when the library exists when the `Reloadable` is created, a library
+    /// that's built later always gets `init`.
+    ///
+    /// Since the host process can end at any point, a state in a file should
+    /// be plain data. Pointers into the host or the library's code won't be
+    /// valid after a restart, and should be set up again in `reload`. The
+    /// library's `deinit` still runs when the `Reloadable` is dropped, so it
+    /// should leave the state in a form that can be picked up again.
+    ///
+    /// Growing or shrinking the `State` resizes the file and maps it again,
+    /// which can move it, in which case the library's `on_state_moved` is
+    /// called. The whole file is always mapped, so [`stable_state`][] and
+    /// [`reserve_state`][] can't keep room for a bigger `State`, and the state
+    /// is never guarded, see [`guard_state`][]. This is only available on Unix.
+    ///
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    /// [`stable_state`]: struct.Builder.html#method.stable_state
+    /// [`reserve_state`]: struct.Builder.html#method.reserve_state
+    /// [`guard_state`]: struct.Builder.html#method.guard_state
+    #[cfg(unix)]
+    pub fn mmap_state<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.mmap_state = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Refuse to load a library whose `State` is bigger than `max_size` bytes.
+    ///
+    /// A library that isn't what it claims to be can report any size for its
+    /// `State`, and allocating a buffer for an absurd size would take down the
+    /// host. Loading fails with [`Error::StateTooLarge`][] instead. The default
+    /// limit is 1 GiB, raise it if your `State` really is bigger.
+    ///
+    /// [`Error::StateTooLarge`]: enum.Error.html#variant.StateTooLarge
+    pub fn max_state_size(mut self, max_size: usize) -> Self {
+        self.max_state_size = max_size;
+        self
+    }
+
+    /// Choose the alignment of the `State` buffer.
+    ///
+    /// The buffer is aligned to 8 bytes by default, which is enough for most
+    /// `State` types. Use [`StateAlignment::CacheLine`][] if the library's
+    /// `State` is aligned to a cache line, or to keep its hot data from
+    /// straddling cache lines.
+    ///
+    /// [`StateAlignment::CacheLine`]: enum.StateAlignment.html#variant.CacheLine
+    pub fn state_alignment(mut self, alignment: StateAlignment) -> Self {
+        self.state_alignment = alignment;
+        self
+    }
+
+    /// Surround the `State` with guard bytes, and panic as soon as the library
+    /// writes over them.
+    ///
+    /// A library that writes past the end of its `State`, for example because
+    /// its `size` is out of date, silently corrupts whatever is next to it on
+    /// the heap. With this enabled, the guards are checked after each call into
+    /// the library, turning that corruption into a panic that names the
+    /// function that did it. This only has an effect in debug builds.
+    pub fn guard_state(mut self, guard: bool) -> Self {
+        self.guard_state = guard;
+        self
+    }
+
+    /// Load the library without calling its `init`, leaving the `State`
+    /// zeroed.
+    ///
+    /// This is for tests and migrations that set up the state themselves, for
+    /// example with [`Reloadable::replace_state_bytes`][], before the library
+    /// starts running. The first `update` then picks up whatever state was put
+    /// there. The library's `deinit` is still called when the `Reloadable` is
+    /// dropped. This has no effect when loading is deferred because the
+    /// library doesn't exist yet, see [`Reloadable::new`][].
+    ///
+    /// [`Reloadable::replace_state_bytes`]: struct.Reloadable.html#method.replace_state_bytes
+    /// [`Reloadable::new`]: struct.Reloadable.html#method.new
+    pub fn skip_init(mut self, skip: bool) -> Self {
+        self.skip_init = skip;
+        self
+    }
+
+    /// Call the library's `reload` right after `init` when it's first loaded,
+    /// as well as on every reload.
+    ///
+    /// By default `reload` only runs when the library is reloaded, since the
+    /// state `init` just set up has nothing to migrate. Enabling this is
+    /// useful when `reload` sets up things that have to be redone for every
+    /// copy of the code, so that it doesn't also have to be done in `init`. If
+    /// this `reload` reports an error, creating the `Reloadable` fails with
+    /// it.
+    pub fn reload_on_initial_load(mut self, reload: bool) -> Self {
+        self.reload_on_initial_load = reload;
+        self
+    }
+
+    /// Load the library and create the `Reloadable`.
+    ///
+    /// See [`Reloadable::new`][].
+    ///
+    /// [`Reloadable::new`]: struct.Reloadable.html#method.new
+    pub fn build(self) -> Result<Reloadable<Host>, Error> {
+        if !self.path.exists() {
+            return self.build_deferred();
+        }
+        self.build_with_args(&())
+    }
+
+    /// Create the `Reloadable` on a new thread, which then makes every call
+    /// into the library.
+    ///
+    /// This is for keeping all of the library's code on one thread, which
+    /// doesn't have to be the host's main thread. The `Reloadable` is created
+    /// on the new thread like [`build`][] would, and stays there. The returned
+    /// [`ThreadHandle`][] sends it calls to make, see [`ThreadHandle::run`][],
+    /// and waits for their results.
+    ///
+    /// If `core` is given, the thread is pinned to the CPU core with that
+    /// number first, so that the library's code always runs on that core. This
+    /// is only supported on Linux and Android, elsewhere `core` is ignored.
+    ///
+    /// If creating the `Reloadable` fails, the thread stops again and this
+    /// returns the error.
+    ///
+    /// ```rust,no_run
+    /// # #[derive(Default)]
+    /// # struct Host;
+    /// let mut app = live_reload::Reloadable::builder("target/debug/libgame.so", Host)
+    ///     .run_on_thread(Some(2))
+    ///     .expect("Should successfully load");
+    /// loop {
+    ///     if app.update().exit_code().is_some() {
+    ///         break;
+    ///     }
+    ///     app.reload().expect("Should safely reload");
+    /// }
+    /// ```
+    ///
+    /// [`build`]: struct.Builder.html#method.build
+    /// [`ThreadHandle`]: struct.ThreadHandle.html
+    /// [`ThreadHandle::run`]: struct.ThreadHandle.html#method.run
+    pub fn run_on_thread(self, core: Option<usize>) -> Result<ThreadHandle<Host>, Error>
+    where
+        Host: Send + 'static,
+    {
+        let (jobs, receiver) = channel::<ThreadJob<Host>>();
+        let (started, start) = channel();
+        let thread = std::thread::Builder::new()
+            .name("live-reload".to_owned())
+            .spawn(move || {
+                let app = match core {
+                    Some(core) => pin_to_core(core).map_err(Error::from),
+                    None => Ok(()),
+                }
+                .and_then(|()| self.build());
+                let mut app = match app {
+                    Ok(app) => app,
+                    Err(err) => {
+                        let _ = started.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = started.send(Ok(()));
+                for job in receiver {
+                    job(&mut app);
+                }
+            })?;
+        let mut handle = ThreadHandle {
+            jobs: Some(jobs),
+            thread: Some(thread),
+        };
+        match start.recv() {
+            Ok(Ok(())) => Ok(handle),
+            Ok(Err(err)) => Err(err),
+            Err(_) => handle.resume_panic(),
+        }
+    }
+
+    /// Load the library and create the `Reloadable`, passing arguments to its
+    /// `init`.
+    ///
+    /// See [`Reloadable::new_with_args`][].
+    ///
+    /// Unlike [`build`][], this doesn't defer loading when the library doesn't
+    /// exist yet, since the arguments would be gone by the time it does.
+    ///
+    /// [`Reloadable::new_with_args`]: struct.Reloadable.html#method.new_with_args
+    /// [`build`]: struct.Builder.html#method.build
+    pub fn build_with_args<A>(self, args: &A) -> Result<Reloadable<Host>, Error> {
+        self.check_copy_dir()?;
+        let initial_strategy = LoadingStrategy {
+            max_retries: 0,
+            load_retries: 0,
+            ..self.loading_strategy.clone()
+        };
+        let hash = hash_library(&self.path).ok();
+        let mut sym = Reloadable::load(&self.path, &initial_strategy)?;
+        sym.hash = hash;
+        if self.verify {
+            sym.verify()?;
+        }
+        let size = sym.state_size();
+        let library_name = sym.name().to_owned();
+        let path = self
+            .path
+            .canonicalize()
+            .map_err(io_context("find", &self.path))?;
+        sym.info = PluginInfo::for_library(&path);
+        let skip_init = self.skip_init;
+        let version = sym.state_version();
+        let mut app = self.into_reloadable(path, Some(sym), library_name)?;
+        // Only a state from a file can already be there
+        let restored = app
+            .state
+            .header()
+            .is_some_and(|header| header.magic == STATE_MAGIC)
+            && app.state.size == size
+            && app.state_header_matches(version);
+        if let Err(err) = app.realloc_buffer(size) {
+            app.sym = None;
+            return Err(err);
+        }
+        if restored {
+            app.restore_state()?;
+            return Ok(app);
+        }
+        app.state.zero();
+        if skip_init {
+            app.initial_reload()?;
+            return Ok(app);
+        }
+        match app.call_init(args) {
+            Ok(ShouldQuit::No) => {
+                app.initial_reload()?;
+                Ok(app)
+            }
+            // Dropping the app will deinit the library
+            Ok(quit) => Err(Error::QuitRequestedAtInit(quit.exit_code().unwrap_or(0))),
+            Err(err) => {
+                // Don't deinit a library that never got initialized
+                app.sym = None;
+                Err(err)
+            }
+        }
+    }
+
+    /// Check that the copies of the library can be made in `copy_dir`.
+    fn check_copy_dir(&self) -> Result<(), Error> {
+        let dir = match self.loading_strategy.copy_dir {
+            Some(ref dir) => dir,
+            None => return Ok(()),
+        };
+        let probe = dir.join(format!(".live-reload-{}", std::process::id()));
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+            .map_err(io_context("create files in", dir))?;
+        std::fs::remove_file(&probe).map_err(io_context("remove", &probe))
+    }
+
+    /// Create a `Reloadable` for a library that hasn't been built yet, which
+    /// gets loaded and initialized by the first `reload` after it appears.
+    fn build_deferred(self) -> Result<Reloadable<Host>, Error> {
+        self.check_copy_dir()?;
+        let name = self.path.file_name().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no library file name")
+        })?;
+        let dir = match self.path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _ => Path::new("."),
+        };
+        let path = dir
+            .canonicalize()
+            .map_err(io_context("find", dir))?
+            .join(name);
+        let mut app = self.into_reloadable(path, None, String::new())?;
+        app.needs_init = true;
+        Ok(app)
+    }
+
+    /// Create the `Reloadable` with the configured options, without calling
+    /// into the library.
+    fn into_reloadable(
+        self,
+        path: PathBuf,
+        sym: Option<AppSym<Host>>,
+        library_name: String,
+    ) -> Result<Reloadable<Host>, Error> {
+        let state = StateBuffer::new(
+            self.state_alignment,
+            self.guard_state && cfg!(debug_assertions),
+        );
+        #[cfg(unix)]
+        let state = match self.mmap_state {
+            Some(ref path) => StateBuffer::mapped(path)?,
+            None => state,
+        };
+        let owns_watcher = self.events.is_none();
+        let events = match self.events {
+            Some(events) => events,
+            None => Box::new(NotifyEvents::new(&self.path, self.watch_mode)?),
+        };
+        let mut app = Reloadable {
+            path,
+            watched_path: std::path::absolute(&self.path)?,
+            sym,
+            dependencies: Vec::new(),
+            companions: Vec::new(),
+            triggers: Vec::new(),
+            candidate: None,
+            previous: Vec::new(),
+            previous_policy: PreviousLibraryPolicy::Close,
+            state: state.with_header(),
+            events,
+            owns_watcher,
+            watch_lost: false,
+            watch_mode: self.watch_mode,
+            library_name,
+            host: ManuallyDrop::new(self.host),
+            host_taken: false,
+            verify: self.verify,
+            trusting: self.trusting,
+            stable_state: self.stable_state.is_some(),
+            max_state_size: self.max_state_size,
+            auto_compact_state: false,
+            in_update: false,
+            held_unloaded: false,
+            dead: false,
+            poisoned: false,
+            needs_init: false,
+            pending_change: false,
+            live_slot: 0,
+            leaked: false,
+            realloc_count: 0,
+            reload_on_initial_load: self.reload_on_initial_load,
+            reload_count: 0,
+            reload_requests: ReloadRequest::NONE,
+            last_reload: None,
+            last_update_duration: None,
+            min_reload_interval: Duration::from_secs(0),
+            build_lock: None,
+            #[cfg(unix)]
+            sigusr1_seen: None,
+            error_policy: Default::default(),
+            #[cfg(feature = "count-allocations")]
+            allocation_check: Default::default(),
+            #[cfg(feature = "count-allocations")]
+            last_update_allocations: None,
+            last_error: None,
+            last_reload_timing: None,
+            timing_callback: None,
+            loaded_callback: None,
+            state_moved_callback: None,
+            should_trigger: None,
+            resources: Vec::new(),
+            before_update: None,
+            loading_strategy: self.loading_strategy,
+        };
+        app.reserve_state(self.stable_state.unwrap_or(0).max(self.reserve_state));
+        Ok(app)
+    }
+}
+
+impl<Host> Reloadable<Host> {
+    /// Create a new Reloadable library.
+    ///
+    /// This takes the path to a dynamic library containing a `RELOAD_API`
+    /// symbol that exports the functions needed for live reloading. In order to
+    /// define this symbol in your own reloadable libraries, see the
+    /// [`live_reload!`][] macro. This will load the library and initialize a
+    /// filesystem watcher pointing to the file in order to know when the
+    /// library has changed.
+    ///
+    /// If the library's `init` asks to quit, this calls `deinit` and fails with
+    /// [`Error::QuitRequestedAtInit`][].
+    ///
+    /// If the library hasn't been built yet, but the directory it will be
+    /// built into exists, loading is deferred: the `Reloadable` starts out
+    /// unloaded, and the first [`reload`][] after the file appears loads it
+    /// and calls `init`. Any error from that `init` is returned by `reload`.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Error::QuitRequestedAtInit`]: enum.Error.html#variant.QuitRequestedAtInit
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    pub fn new<P: AsRef<Path>>(path: P, host: Host) -> Result<Self, Error> {
+        Builder::new(path, host).build()
+    }
+
+    /// Create a new Reloadable library, passing arguments to its `init`.
+    ///
+    /// This works like [`new`][], but `args` is passed along to the library's
+    /// `init` function. The library has to declare a matching `init_args` type
+    /// in its [`live_reload!`][] invocation, otherwise this will fail with
+    /// [`Error::MismatchedInitArgs`][]. Since `init` is only called once, the
+    /// arguments are only borrowed for the duration of that call.
+    ///
+    /// [`new`]: struct.Reloadable.html#method.new
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Error::MismatchedInitArgs`]: enum.Error.html#variant.MismatchedInitArgs
+    pub fn new_with_args<P: AsRef<Path>, A>(path: P, host: Host, args: &A) -> Result<Self, Error> {
+        Builder::new(path, host).build_with_args(args)
+    }
+
+    /// Create a new Reloadable library without checking the `Host` type.
+    ///
+    /// Normally, every time the library is loaded its `Host` type is checked
+    /// against the host's, and a mismatch fails with
+    /// [`Error::MismatchedHost`][]. The check compares the size and alignment
+    /// of `Host`, since that's all that can be compared between separately
+    /// compiled programs, so it catches most but not all mistakes.
+    ///
+    /// This skips that check entirely, along with the check that the library
+    /// was built against a compatible version of `live-reload`. If the library
+    /// was built against a different `Host`, calling into it will read the
+    /// host's memory with the wrong layout, so only use this if you're sure
+    /// both sides are always rebuilt together.
+    ///
+    /// [`Error::MismatchedHost`]: enum.Error.html#variant.MismatchedHost
+    pub fn new_unchecked<P: AsRef<Path>>(path: P, host: Host) -> Result<Self, Error> {
+        Builder::new(path, host).check_host(false).build()
+    }
+
+    /// Create a new Reloadable library that trusts the library completely.
+    ///
+    /// This skips the checks of [`new_unchecked`][], and also:
+    ///
+    /// - the magic number at the start of `RELOAD_API`, so anything exported
+    ///   under that name is called as if [`live_reload!`][] made it,
+    /// - the input type's size on every [`update_with_input`][],
+    /// - the `state_version` on every reload, so a `State` is always
+    ///   reinterpreted by the new library instead of being reset.
+    ///
+    /// Any mismatch that these would have caught is undefined behavior
+    /// instead of an error. This is only meant for a tight iteration loop
+    /// where the host and the library come from the same build, and the
+    /// checks' small cost matters. The checks that the host asked for, like
+    /// [`Builder::guard_state`][] and [`LoadingStrategy::required_symbols`][],
+    /// still happen.
+    ///
+    /// [`new_unchecked`]: struct.Reloadable.html#method.new_unchecked
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`update_with_input`]: struct.Reloadable.html#method.update_with_input
+    /// [`Builder::guard_state`]: struct.Builder.html#method.guard_state
+    /// [`LoadingStrategy::required_symbols`]: struct.LoadingStrategy.html#structfield.required_symbols
+    pub fn new_trusting<P: AsRef<Path>>(path: P, host: Host) -> Result<Self, Error> {
+        Builder::new(path, host).trusting(true).build()
+    }
+
+    /// Start configuring a new Reloadable library with a [`Builder`][].
+    ///
+    /// [`Builder`]: struct.Builder.html
+    pub fn builder<P: AsRef<Path>>(path: P, host: Host) -> Builder<Host> {
+        Builder::new(path, host)
+    }
+
+    /// Look at what a library exports without loading it into a `Reloadable`.
+    ///
+    /// This opens the library, finds its `RELOAD_API`, and reports what it
+    /// contains, without calling any of its lifecycle functions (other than
+    /// `size`) or creating a filesystem watcher. It's meant for diagnosing
+    /// libraries that won't load.
+    ///
+    /// This fails if the library doesn't export `RELOAD_API` or was built
+    /// against an incompatible version of `live-reload`, since nothing else in
+    /// it can be trusted in that case. A mismatched `Host` type is reported in
+    /// the returned [`LibraryInfo`][] instead.
+    ///
+    /// [`LibraryInfo`]: struct.LibraryInfo.html
+    pub fn inspect<P: AsRef<Path>>(path: P) -> Result<LibraryInfo, Error> {
+        let sym = AppSym::<Host>::new(path.as_ref(), &LoadingStrategy::default())?;
+        let host_matches = match sym.verify() {
+            Ok(()) => true,
+            Err(Error::MismatchedHost) => false,
+            Err(err) => return Err(err),
+        };
+        Ok(LibraryInfo {
+            path: path.as_ref().to_owned(),
+            name: sym.name().to_owned(),
+            abi_version: sym.abi_version(),
+            state_size: sym.state_size(),
+            init_args_size: sym.init_args_size(),
+            host_matches,
+        })
+    }
+
+    /// Check everything that loading a library depends on, and report each
+    /// check separately.
+    ///
+    /// Where [`inspect`][] stops at the first problem, this keeps checking as
+    /// far as it can, for tools that show a checklist of what's wrong with a
+    /// library that won't load. Like `inspect`, it opens the library without
+    /// calling any of its lifecycle functions (other than `size`) or creating
+    /// a filesystem watcher, and never fails.
+    ///
+    /// ```rust,no_run
+    /// # mod host_api { pub struct Host; }
+    /// # use host_api::Host;
+    /// let report = live_reload::Reloadable::<Host>::diagnose("target/debug/libgame.so");
+    /// if !report.would_load() {
+    ///     println!("{:#?}", report);
+    /// }
+    /// ```
+    ///
+    /// [`inspect`]: struct.Reloadable.html#method.inspect
+    pub fn diagnose<P: AsRef<Path>>(path: P) -> LoadDiagnostics {
+        let path = path.as_ref();
+        let mut report = LoadDiagnostics {
+            path: path.to_owned(),
+            file_exists: path.is_file(),
+            is_rlib: is_rlib(path),
+            ..Default::default()
+        };
+        if !report.file_exists {
+            return report;
+        }
+        let library = match open_library(path, &LoadingStrategy::default()) {
+            Ok(library) => library,
+            Err(err) => {
+                report.open_error = Some(err.to_string());
+                return report;
+            }
+        };
+        let api = match unsafe { library.get::<*const u64>(b"RELOAD_API") } {
+            Ok(sym) => *sym,
+            Err(_) => return report,
+        };
+        report.has_reload_api = true;
+        let api = match unsafe { *api } {
+            internals::MAGIC => Api::Rust(api as *const internals::ReloadApi<Host>),
+            internals::MAGIC_C => Api::C(api as *const internals::ReloadApiC<Host>),
+            _ => return report,
+        };
+        report.is_reload_api = true;
+        let sym = AppSym {
+            _lib: ManuallyDrop::new(library),
+            api,
+            path: path.to_owned(),
+            fd_file: None,
+            info: PluginInfo::default(),
+            hash: None,
+            leak: false,
+        };
+        report.abi_version = Some(sym.abi_version());
+        let expected = match sym.api {
+            Api::Rust(_) => internals::ABI_VERSION,
+            Api::C(_) => internals::ABI_VERSION_C,
+        };
+        report.abi_matches = sym.abi_version() == expected;
+        if !report.abi_matches {
+            return report;
+        }
+        report.host_matches =
+            sym.host_layout() == (std::mem::size_of::<Host>(), std::mem::align_of::<Host>());
+        report.name = Some(sym.name().to_owned());
+        report.state_size = Some(sym.state_size());
+        report.init_args_size = Some(sym.init_args_size());
+        report
+    }
+
+    /// Load every reloadable library in a directory.
+    ///
+    /// This looks at each dynamic library directly inside `dir`, in order of
+    /// their paths, and creates a `Reloadable` for every one that exports a
+    /// `RELOAD_API` for this `Host` type, calling `host_factory` to make a new
+    /// host for each of them. Files that aren't reloadable libraries, or that
+    /// fail to load or initialize, are skipped, so that one broken mod doesn't
+    /// stop the others from loading. Use [`inspect`][] to find out why a file
+    /// was skipped.
+    ///
+    /// This only fails if the directory itself can't be read.
+    ///
+    /// [`inspect`]: struct.Reloadable.html#method.inspect
+    pub fn discover_plugins<P, F>(dir: P, mut host_factory: F) -> Result<Vec<Self>, Error>
+    where
+        P: AsRef<Path>,
+        F: FnMut() -> Host,
+    {
+        let mut paths = Vec::new();
+        let dir = dir.as_ref();
+        for entry in std::fs::read_dir(dir).map_err(io_context("read", dir))? {
+            let path = entry.map_err(io_context("read", dir))?.path();
+            let is_library = path.extension() == Some(std::env::consts::DLL_EXTENSION.as_ref());
+            // Skip the copies that get loaded on Windows
+            let is_live_copy = path
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().ends_with(".live"));
+            if is_library && !is_live_copy {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut plugins = Vec::new();
+        for path in paths {
+            match Self::inspect(&path) {
+                Ok(ref info) if info.host_matches => {}
+                _ => continue,
+            }
+            if let Ok(plugin) = Self::new(&path, host_factory()) {
+                plugins.push(plugin);
+            }
+        }
+        Ok(plugins)
+    }
+
+    /// Reload the library if it has changed, otherwise do nothing.
+    ///
+    /// This will consult with the filesystem watcher, and if the library has
+    /// been recreated or updated, it will reload the library. See
+    /// [`reload_now`][] for details on what happens when a library is reloaded.
+    ///
+    /// If the library changes again while it's being reloaded, it's reloaded
+    /// again right away, so that the latest version is loaded when this
+    /// returns. This happens at most 3 times per call, any change after that
+    /// is handled by the next call.
+    ///
+    /// If the filesystem watcher reported an error since the last call, this
+    /// returns it as [`Error::Watch`][] without reloading. Changes seen
+    /// before the error are kept for the next call.
+    ///
+    /// If the [`ReloadErrorPolicy`][] is `KeepRunning`, a failed reload is
+    /// stored in [`last_error`][] instead of being returned. The new library is
+    /// then loaded and checked before the old one is unloaded, like with
+    /// [`LoadingStrategy::load_new_first`][], so a broken build leaves the old
+    /// library running in [`update`][] until the library changes again.
+    ///
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    /// [`Error::Watch`]: enum.Error.html#variant.Watch
+    /// [`LoadingStrategy::load_new_first`]: struct.LoadingStrategy.html#structfield.load_new_first
+    /// [`ReloadErrorPolicy`]: enum.ReloadErrorPolicy.html
+    /// [`last_error`]: struct.Reloadable.html#method.last_error
+    /// [`update`]: struct.Reloadable.html#method.update
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.release_expired_previous();
+        match self.reload_if_changed() {
+            Ok(reloaded) => {
+                if reloaded {
+                    self.last_error = None;
+                }
+                Ok(())
+            }
+            Err(err) => match self.error_policy {
+                ReloadErrorPolicy::Abort => Err(err),
+                ReloadErrorPolicy::KeepRunning => {
+                    self.last_error = Some(err);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// Check whether [`reload`][] would reload the library, without reloading
+    /// it.
+    ///
+    /// This is true if the library changed on disk since the last reload, or
+    /// if no library is loaded because the last reload failed. It's meant for
+    /// showing that a reload is available, or for tests. Any changes it sees
+    /// are kept for the next call to `reload`, which is why this needs the
+    /// `Reloadable` mutably.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    pub fn needs_reload(&mut self) -> Result<bool, Error> {
+        self.pending_change = self.take_changes()?;
+        Ok(!self.dead && !self.held_unloaded && (self.pending_change || self.sym.is_none()))
+    }
+
+    /// Reload the library if it changed, see `reload`. This returns whether
+    /// the library was reloaded, or checked and found to be the loaded one.
+    fn reload_if_changed(&mut self) -> Result<bool, Error> {
+        let mut should_reload = self.take_changes()?;
+        if self.dead || self.held_unloaded || !(should_reload || self.sym.is_none()) {
+            return Ok(false);
+        }
+        if self.needs_init && !self.path.exists() {
+            // Still waiting for the first build
+            return Ok(false);
+        }
+        // Another save can arrive while reloading, so keep going until the
+        // latest version is loaded, but give up eventually if saves keep coming
+        let mut reloads = 0;
+        loop {
+            if reloads == MAX_RELOADS_PER_CALL || self.reload_throttled() || self.build_locked() {
+                self.pending_change = true;
+                return Ok(reloads > 0);
+            }
+            self.reload_now()?;
+            reloads += 1;
+            should_reload = self.take_changes()?;
+            if !should_reload {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Check whether a loaded library was reloaded too recently to reload it
+    /// again, see `set_min_reload_interval`.
+    fn reload_throttled(&self) -> bool {
+        self.sym.is_some()
+            && self
+                .last_reload
+                .is_some_and(|last| last.elapsed() < self.min_reload_interval)
+    }
+
+    /// Check whether the build lock file exists, see `set_build_lock`.
+    fn build_locked(&self) -> bool {
+        self.build_lock.as_ref().is_some_and(|lock| lock.exists())
+    }
+
+    /// Check whether the library changed or a reload was requested since the
+    /// last check.
+    fn take_changes(&mut self) -> Result<bool, Error> {
+        let mut changed = std::mem::take(&mut self.pending_change);
+        while let Some(evt) = self.next_event() {
+            use DebouncedEvent::*;
+            match evt {
+                NoticeWrite(ref path)
+                | Write(ref path)
+                | Create(ref path)
+                | Rename(_, ref path) => {
+                    changed |= self.is_trigger(path);
+                }
+                // A removed directory isn't always reported while the library
+                // is still mapped from it, so any removal there is suspect
+                NoticeRemove(ref path) | Remove(ref path)
+                    if self.is_library_dir(path)
+                        || path.parent().is_some_and(|dir| self.is_library_dir(dir)) =>
+                {
+                    self.watch_lost = self.owns_watcher;
+                }
+                Error(err, _) => {
+                    // Keep what was seen so far for the next call
+                    self.pending_change = changed;
+                    return Err(err.into());
+                }
+                _ => {}
+            }
+        }
+        // The library may have been rebuilt before its directory is watched again
+        if self.watch_lost && self.watched_path.parent().is_some_and(Path::is_dir) {
+            self.pending_change = changed;
+            self.rewatch()?;
+            changed = std::mem::take(&mut self.pending_change);
+        }
+        #[cfg(unix)]
+        {
+            if let Some(ref mut seen) = self.sigusr1_seen {
+                let count = SIGUSR1_COUNT.load(Ordering::SeqCst);
+                if count != *seen {
+                    *seen = count;
+                    changed = true;
+                }
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Check whether `path`, as reported by the watcher, is the library's
+    /// directory.
+    fn is_library_dir(&self, path: &Path) -> bool {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_owned());
+        self.watched_path.parent() == Some(path.as_path())
+            || self.path.parent() == Some(path.as_path())
+    }
+
+    /// Watch the library's directory again.
+    ///
+    /// The default watcher stops for good when the directory it watches is
+    /// removed, for example by `cargo clean`, even once the directory is
+    /// created again. When the watcher reports that the directory or a file
+    /// in it was removed, [`reload`][] calls this by itself as soon as the
+    /// directory exists, so this is only needed when the watcher stopped in a
+    /// way that wasn't noticed. The library is checked for changes on the next
+    /// `reload`, since it may have been rebuilt while nothing was watching it.
+    ///
+    /// This fails with [`Error::Watch`][] if the directory doesn't exist. If
+    /// the [`Builder`][] was given custom [`events`][], this only checks the
+    /// library for changes on the next reload. Watchers of other directories,
+    /// for [`watch_additional_library`][] and [`add_reload_trigger`][], aren't
+    /// recreated.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`Error::Watch`]: enum.Error.html#variant.Watch
+    /// [`Builder`]: struct.Builder.html
+    /// [`events`]: struct.Builder.html#method.events
+    /// [`watch_additional_library`]: struct.Reloadable.html#method.watch_additional_library
+    /// [`add_reload_trigger`]: struct.Reloadable.html#method.add_reload_trigger
+    pub fn rewatch(&mut self) -> Result<(), Error> {
+        if self.owns_watcher {
+            self.events = Box::new(NotifyEvents::new(&self.watched_path, self.watch_mode)?);
+        }
+        self.watch_lost = false;
+        self.pending_change = true;
+        Ok(())
+    }
+
+    /// Get the next event from the event source, or from the watcher of a
+    /// companion or a reload trigger, see `watch_additional_library` and
+    /// `add_reload_trigger`.
+    fn next_event(&mut self) -> Option<DebouncedEvent> {
+        self.events.try_next().or_else(|| {
+            let companions = self
+                .companions
+                .iter_mut()
+                .map(|companion| &mut companion.events);
+            let triggers = self.triggers.iter_mut().map(|trigger| &mut trigger.events);
+            companions
+                .chain(triggers)
+                .filter_map(Option::as_mut)
+                .find_map(|events| events.try_next())
+        })
+    }
+
+    /// Load the library at `path` on the side and check that it could replace
+    /// the loaded library, without touching the loaded library or the state.
+    ///
+    /// This is for checking a new build before switching to it, so that a
+    /// broken build can't take down the running session. The candidate is
+    /// loaded from a copy of `path`, and goes through the same checks as a
+    /// reload: it has to export a `RELOAD_API` built against this version of
+    /// `live-reload`, with a matching `Host` type (unless the `Reloadable` was
+    /// created with [`new_unchecked`][]), and a `State` that fits within
+    /// [`Builder::max_state_size`][] and [`Builder::stable_state`][]. Its
+    /// init arguments and input types have to match the loaded library's too,
+    /// since the host keeps passing the same ones. Finally, if the library
+    /// gives [`live_reload!`][] a `self_test` function, that's called, and an
+    /// error it reports fails the validation as [`Error::Library`][].
+    ///
+    /// If the candidate passes, it's kept loaded until [`commit_candidate`][]
+    /// switches to it, replacing any earlier candidate. Otherwise the error is
+    /// returned and nothing changes.
+    ///
+    /// [`new_unchecked`]: struct.Reloadable.html#method.new_unchecked
+    /// [`Builder::max_state_size`]: struct.Builder.html#method.max_state_size
+    /// [`Builder::stable_state`]: struct.Builder.html#method.stable_state
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    /// [`commit_candidate`]: struct.Reloadable.html#method.commit_candidate
+    pub fn validate_candidate<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let path = path.canonicalize().map_err(io_context("find", path))?;
+        // The loader would hand back the old candidate if it has the same name
+        self.candidate = None;
+        let copy_path =
+            path.with_extension(format!("candidate.{}", std::env::consts::DLL_EXTENSION));
+        let hash = hash_library(&path).ok();
+        let strategy = &self.loading_strategy;
+        let mut sym = strategy.retry_load(|| {
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            {
+                if strategy.map_original {
+                    if let Ok((file, fd_path)) = open_library_fd(&path) {
+                        return AppSym::<Host>::new(fd_path, strategy)
+                            .map(|sym| sym.loaded_through(file, &path));
+                    }
+                }
+            }
+            copy_library(&path, &copy_path, strategy)?;
+            let sym = AppSym::new(&copy_path, strategy);
+            if cfg!(unix) {
+                let _ = std::fs::remove_file(&copy_path);
+            }
+            sym
+        })?;
+        sym.hash = hash;
+        if self.verify {
+            sym.verify()?;
+        }
+        self.check_state_size(sym.state_size())?;
+        if let Some(ref live) = self.sym {
+            if sym.init_args_size() != live.init_args_size() {
+                return Err(Error::MismatchedInitArgs);
+            }
+            if sym.input_size() != live.input_size() {
+                return Err(Error::MismatchedInput);
+            }
+        }
+        if let Some(self_test) = sym.rust_api().and_then(|api| api.self_test) {
+            let mut error = None;
+            self_test(&mut error);
+            if let Some(message) = error {
+                return Err(Error::Library(message));
+            }
+        }
+        self.candidate = Some((path, sym));
+        Ok(())
+    }
+
+    /// Switch to the library that passed [`validate_candidate`][].
+    ///
+    /// This reloads as in [`reload_now`][], keeping the state, except that the
+    /// already loaded candidate is switched to instead of loading the library
+    /// again. Like with [`set_path`][], the `Reloadable` watches the
+    /// candidate's path from then on.
+    ///
+    /// This fails with [`Error::NoCandidate`][] if no candidate is waiting,
+    /// and with [`Error::ReentrantReload`][] if it's called while the
+    /// library's `update` is running, in which case the candidate is kept.
+    ///
+    /// [`validate_candidate`]: struct.Reloadable.html#method.validate_candidate
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    /// [`set_path`]: struct.Reloadable.html#method.set_path
+    /// [`Error::NoCandidate`]: enum.Error.html#variant.NoCandidate
+    /// [`Error::ReentrantReload`]: enum.Error.html#variant.ReentrantReload
+    pub fn commit_candidate(&mut self) -> Result<(), Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        let (path, sym) = self.candidate.take().ok_or(Error::NoCandidate)?;
+        if self.owns_watcher && path != self.path {
+            self.events = Box::new(NotifyEvents::new(&path, self.watch_mode)?);
+        }
+        self.watched_path = path.clone();
+        self.path = path;
+        let was_loaded = self.sym.is_some();
+        let result = self.reload_library(Some(sym), None);
+        self.loaded_changed(was_loaded);
+        result
+    }
+
+    /// Check whether a change to `path` should reload the library.
+    ///
+    /// A build that replaces the library by renaming a new file over it, or by
+    /// pointing a link at a new file, can leave `self.path` resolved to the
+    /// file that was replaced. When the changed file isn't that one, the
+    /// library's path is resolved again, and if that's the changed file, it
+    /// becomes the library's path from then on.
+    fn is_trigger(&mut self, path: &Path) -> bool {
+        if let Some(ref should_trigger) = self.should_trigger {
+            return should_trigger(path);
+        }
+        // The file may be gone already, like a temporary file that was
+        // renamed over the library
+        let changed = match path.canonicalize() {
+            Ok(changed) => changed,
+            Err(_) => return false,
+        };
+        if same_path(&changed, &self.path)
+            || self
+                .companions
+                .iter()
+                .any(|companion| same_path(&changed, &companion.path))
+            || self
+                .triggers
+                .iter()
+                .any(|trigger| same_path(&changed, &trigger.path) || same_path(path, &trigger.path))
+        {
+            return true;
+        }
+        match self.watched_path.canonicalize() {
+            Ok(resolved) if same_path(&changed, &resolved) => {
+                self.path = resolved;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Decide which changed paths reload the library.
+    ///
+    /// By default, only a change to the library's own file reloads it,
+    /// comparing canonical paths. If the build writes somewhere else and then
+    /// links or copies the result to the watched path, the events may only
+    /// name the build output. `should_trigger` replaces the built-in check: it
+    /// gets each path that the event source reports as written, created or
+    /// renamed to, exactly as reported, and any path it returns true for
+    /// reloads the library. To keep the built-in check as well, compare
+    /// against the library's canonical path in `should_trigger`.
+    ///
+    /// The default watcher only reports changes in the library's directory,
+    /// so a custom event source from [`Builder::events`][] may be needed too.
+    ///
+    /// [`Builder::events`]: struct.Builder.html#method.events
+    pub fn set_should_trigger<F>(&mut self, should_trigger: F)
+    where
+        F: Fn(&Path) -> bool + Send + 'static,
+    {
+        self.should_trigger = Some(Box::new(should_trigger));
+    }
+
+    /// Switch to the library at a different path, and load it right away.
+    ///
+    /// This reloads as in [`reload_now`][], except from the new path, and from
+    /// then on watches the new path for changes instead. If the [`Builder`][]
+    /// was given custom [`events`][], those are kept as they are.
+    ///
+    /// The state is kept if the new library has the same package name as the
+    /// old one, for example a different build of the same library. Otherwise
+    /// its `State` has nothing to do with the old one, so the state is zeroed
+    /// before the new library's `reload` is called. This happens on any reload
+    /// that changes the package name, not just here. Call [`reset`][] after
+    /// switching to run the new library's `init` on the state instead.
+    ///
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    /// [`reset`]: struct.Reloadable.html#method.reset
+    /// [`Builder`]: struct.Builder.html
+    /// [`events`]: struct.Builder.html#method.events
+    pub fn set_path<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        let path = path.as_ref();
+        if self.owns_watcher {
+            self.events = Box::new(NotifyEvents::new(path, self.watch_mode)?);
+        }
+        self.path = path.canonicalize().map_err(io_context("find", path))?;
+        self.watched_path = std::path::absolute(path)?;
+        self.reload_now()?;
+        Ok(())
+    }
+
+    /// Switch to the same library built with a different Cargo profile, and
+    /// load it right away.
+    ///
+    /// The library has to be in a profile's directory, like
+    /// `target/debug/libgame.so`, and this switches to the library of the same
+    /// name in the other profile's directory next to it, like
+    /// `target/release/libgame.so`. This is meant for comparing the
+    /// performance of both builds without restarting, so as with
+    /// [`set_path`][], the state is kept.
+    ///
+    /// This fails with [`Error::Io`][] without changing anything if the
+    /// library isn't in a profile's directory, or if the other profile's
+    /// library doesn't exist.
+    ///
+    /// [`set_path`]: struct.Reloadable.html#method.set_path
+    /// [`Error::Io`]: enum.Error.html#variant.Io
+    pub fn switch_profile(&mut self, profile: Profile) -> Result<(), Error> {
+        use std::io::{Error as IoError, ErrorKind};
+
+        let target_dir = self
+            .path
+            .parent()
+            .filter(|dir| Profile::of_dir(dir).is_some())
+            .and_then(Path::parent)
+            .ok_or_else(|| {
+                IoError::new(
+                    ErrorKind::InvalidInput,
+                    "the library isn't in a Cargo profile directory",
+                )
+            })?;
+        let path = target_dir
+            .join(profile.dir_name())
+            .join(self.path.file_name().unwrap());
+        if !path.exists() {
+            let message = format!("{} doesn't exist", path.display());
+            return Err(IoError::new(ErrorKind::NotFound, message).into());
+        }
+        self.set_path(path)
+    }
+
+    /// Get the Cargo profile of the library, if it's in a profile's directory.
+    ///
+    /// See [`switch_profile`][].
+    ///
+    /// [`switch_profile`]: struct.Reloadable.html#method.switch_profile
+    pub fn profile(&self) -> Option<Profile> {
+        Profile::of_dir(self.path.parent()?)
+    }
+
+    /// Immediately reload the library without waiting for a change to be
+    /// reported.
+    ///
+    /// This first calls `unload` on the currently loaded library, then unloads
+    /// the dynamic library. Next, it loads the new dynamic library, and calls
+    /// `reload` on that. If the new library fails to load, this method will
+    /// return an `Err` and the `Reloadable` will be left with no library
+    /// loaded.
+    ///
+    /// If the library file has exactly the same contents as the loaded
+    /// library, for example because a rebuild produced the same output, none
+    /// of that happens and this returns [`ReloadOutcome::Unchanged`][]. The
+    /// file is hashed on every call to compare it.
+    ///
+    /// With [`LoadingStrategy::load_new_first`][], or if the
+    /// [`ReloadErrorPolicy`][] is `KeepRunning`, the new library is loaded and
+    /// checked before the old one's `unload` is called instead. If it fails to
+    /// load, the old library stays loaded and untouched.
+    ///
+    /// If the new library has a different package name than the old one, the
+    /// state is zeroed before `reload` is called, see [`set_path`][].
+    ///
+    /// The host also keeps a header in front of the `State` recording the
+    /// `state_version` of the library that last initialized or reloaded it. If
+    /// the new library has a different `state_version`, or the header was
+    /// overwritten, the state is reset instead of being reinterpreted: it's
+    /// zeroed, and the new library's `init` is called instead of `reload`. A
+    /// library that takes init arguments gets `reload` on the zeroed state,
+    /// since there are no arguments to pass it. If `init` asks to quit, this
+    /// returns [`Error::QuitRequestedAtInit`][].
+    ///
+    /// This fails with [`Error::ReentrantReload`][] if it's called while the
+    /// library's `update` is running, for example from a host service that the
+    /// library called, since that would unload the code that's currently
+    /// executing.
+    ///
+    /// This also brings back a library that was unloaded with [`unload`][].
+    ///
+    /// If the library's `unload` or `reload` reports an error, the reload still
+    /// finishes, and then the error is returned as [`Error::Library`][].
+    ///
+    /// [`LoadingStrategy::load_new_first`]: struct.LoadingStrategy.html#structfield.load_new_first
+    /// [`set_path`]: struct.Reloadable.html#method.set_path
+    /// [`update`]: struct.Reloadable.html#method.update
+    /// [`unload`]: struct.Reloadable.html#method.unload
+    /// [`Error::ReentrantReload`]: enum.Error.html#variant.ReentrantReload
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    /// [`Error::QuitRequestedAtInit`]: enum.Error.html#variant.QuitRequestedAtInit
+    /// [`ReloadOutcome::Unchanged`]: enum.ReloadOutcome.html#variant.Unchanged
+    /// [`ReloadErrorPolicy`]: enum.ReloadErrorPolicy.html
+    pub fn reload_now(&mut self) -> Result<ReloadOutcome, Error> {
+        self.reload_now_with(RecoveryMode::Normal)
+    }
+
+    /// Reload the library like [`reload_now`][], choosing whether the old
+    /// library's `unload` is called.
+    ///
+    /// A library made with [`live_reload!`][] catches a panic in its `update`
+    /// and reports it as [`Error::Panicked`][], and the `Reloadable` is marked
+    /// as poisoned, see [`is_poisoned`][]. The old library's `unload` isn't
+    /// called on the next reload or [`unload`][], since it's likely to panic
+    /// again on the same state, and the library is reloaded even if the file
+    /// is unchanged. This happens with any `RecoveryMode`, and
+    /// [`RecoveryMode::SkipUnload`][] does the same for a library that isn't
+    /// poisoned. The flag is cleared once the old library is closed.
+    ///
+    /// ```rust,no_run
+    /// # use live_reload::{Error, RecoveryMode, Reloadable};
+    /// # let mut app: Reloadable<()> = unimplemented!();
+    /// if let Err(Error::Panicked(message)) = app.try_update() {
+    ///     eprintln!("update panicked: {}", message);
+    ///     // `unload` would be skipped anyway, since `update` panicked
+    ///     app.reload_now_with(RecoveryMode::SkipUnload).unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Error::Panicked`]: enum.Error.html#variant.Panicked
+    /// [`is_poisoned`]: struct.Reloadable.html#method.is_poisoned
+    /// [`unload`]: struct.Reloadable.html#method.unload
+    /// [`RecoveryMode::SkipUnload`]: enum.RecoveryMode.html#variant.SkipUnload
+    pub fn reload_now_with(&mut self, mode: RecoveryMode) -> Result<ReloadOutcome, Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        if mode == RecoveryMode::SkipUnload && self.sym.is_some() && !self.dead {
+            self.poisoned = true;
+        }
+        let hash = hash_library(&self.path).ok();
+        let unchanged = match self.sym {
+            Some(ref sym) => {
+                !self.poisoned
+                    && hash.is_some()
+                    && sym.hash == hash
+                    && self.companions.iter().all(Companion::is_unchanged)
+            }
+            None => false,
+        };
+        if unchanged || self.dead {
+            self.pending_change = false;
+            return Ok(ReloadOutcome::Unchanged);
+        }
+        self.reload_unchecked(hash)?;
+        Ok(ReloadOutcome::Reloaded)
+    }
+
+    /// Reload the library like `reload_now`, even if the file is unchanged.
+    /// `hash` is the hash of the library file, if it's already known.
+    fn reload_unchecked(&mut self, hash: Option<u64>) -> Result<(), Error> {
+        let hash = hash.or_else(|| hash_library(&self.path).ok());
+        let was_loaded = self.sym.is_some();
+        let result = self.reload_library(None, hash);
+        self.loaded_changed(was_loaded);
+        result
+    }
+
+    /// Reload the library, see `reload_now`. If `new_sym` is given, it's
+    /// switched to instead of loading the library again, otherwise `hash` is
+    /// the hash of the library file that gets loaded.
+    fn reload_library(
+        &mut self,
+        mut new_sym: Option<AppSym<Host>>,
+        hash: Option<u64>,
+    ) -> Result<(), Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        if self.dead {
+            return Ok(());
+        }
+        let mut timing = ReloadTiming::default();
+
+        // With companions, everything is loaded before anything is unloaded, so
+        // that one failing to load leaves them all running
+        let new_first = self.loading_strategy.load_new_first
+            || self.error_policy == ReloadErrorPolicy::KeepRunning
+            || !self.companions.is_empty();
+        if new_sym.is_none() && new_first && self.sym.is_some() {
+            let start = Instant::now();
+            let mut sym = self.load_copy()?;
+            sym.hash = hash;
+            if self.verify {
+                sym.verify()?;
+            }
+            self.check_state_size(sym.state_size())?;
+            timing.dlopen = start.elapsed();
+            new_sym = Some(sym);
+        }
+        let mut companion_syms = Vec::with_capacity(self.companions.len());
+        for companion in &mut self.companions {
+            companion_syms.push(companion.load(
+                &self.loading_strategy,
+                self.verify,
+                self.max_state_size,
+            )?);
+        }
+
+        self.held_unloaded = false;
+        self.pending_change = false;
+        if self.sym.is_some() {
+            self.release_resources();
+        }
+        // A failing hook doesn't stop the reload, the first error is reported
+        // once the new library is in place.
+        let mut error = None;
+        let start = Instant::now();
+        if let (Some(ref sym), false) = (&self.sym, self.poisoned) {
+            let state = Self::get_state_ptr(&mut self.state);
+            sym.unload(&mut self.host, state, &mut error);
+        }
+        self.unload_companions(&mut error);
+        if let Some(ref sym) = self.sym {
+            self.reload_requests |= sym.take_reload_requests();
+        }
+        timing.unload = start.elapsed();
+        self.check_state_guards("unload");
+
+        let start = Instant::now();
+        self.poisoned = false;
+        if let Some(old) = self.sym.take() {
+            if self.previous_policy != PreviousLibraryPolicy::Close {
+                self.previous.push((old, Instant::now()));
+            }
+        }
+        for companion in &mut self.companions {
+            companion.sym = None;
+        }
+        // Dependencies go last, since the library may still have used them
+        for dependency in self.dependencies.iter_mut().rev() {
+            dependency.library = None;
+        }
+        timing.dlclose = start.elapsed();
+
+        for dependency in &mut self.dependencies {
+            let library = open_library(&dependency.path, &self.loading_strategy)
+                .map_err(io_context("load", &dependency.path))?;
+            dependency.library = Some(library);
+        }
+
+        let mut sym = match new_sym {
+            Some(sym) => sym,
+            None => {
+                let start = Instant::now();
+                // The loader would hand back a library that's still open
+                let mut sym = if self.leaked || !self.previous.is_empty() {
+                    self.load_copy()?
+                } else {
+                    Self::load(&self.path, &self.loading_strategy)?
+                };
+                sym.hash = hash;
+                if self.verify {
+                    sym.verify()?;
+                }
+                timing.dlopen = start.elapsed();
+                sym
+            }
+        };
+        self.next_live_slot();
+        sym.info = PluginInfo::for_library(&self.path);
+
+        let header_matches = self.trusting || self.state_header_matches(sym.state_version());
+        // @Avoid reallocating if unnecessary
+        let moved_from = self.realloc_buffer(sym.state_size())?;
+        // A different library's State is unrelated to the old bytes
+        let mut reset = false;
+        if sym.name() != self.library_name {
+            self.state.zero();
+            self.library_name = sym.name().to_owned();
+        } else if !header_matches {
+            // Don't let the new library reinterpret a State with another layout
+            self.state.zero();
+            reset = sym.init_args_size() == 0;
+        } else if let Some(old_base) = moved_from {
+            self.state_moved(sym.state_moved(), old_base);
+        }
+        let start = Instant::now();
+        let mut reload_error = None;
+        let mut quit = ShouldQuit::No;
+        let mut init_result = Ok(());
+        if self.needs_init {
+            self.sym = Some(sym);
+            init_result = self.deferred_init();
+        } else {
+            let state = Self::get_state_ptr(&mut self.state);
+            if reset {
+                let args = &() as *const ();
+                quit = sym.init(&mut self.host, state, args, &mut reload_error);
+                self.check_state_guards("init");
+            } else {
+                sym.reload(&mut self.host, state, &mut reload_error);
+                self.check_state_guards("reload");
+            }
+            self.sym = Some(sym);
+            self.write_state_header();
+            self.reload_count += 1;
+            self.last_reload = Some(Instant::now());
+            for resource in &mut self.resources {
+                (resource.acquire)(&mut self.host);
+            }
+        }
+        // The companions are reloaded after the library, like they're unloaded
+        for (companion, sym) in self.companions.iter_mut().zip(companion_syms) {
+            let mut companion_error = None;
+            companion.install(sym, &mut self.host, &mut companion_error);
+            reload_error = reload_error.or(companion_error);
+        }
+        init_result?;
+        timing.reload_hook = start.elapsed();
+
+        if let Some(ref mut callback) = self.timing_callback {
+            callback(&timing);
+        }
+        self.last_reload_timing = Some(timing);
+        match (error.or(reload_error), quit.exit_code()) {
+            (Some(message), _) => Err(Error::Library(message)),
+            (None, Some(code)) => Err(Error::QuitRequestedAtInit(code)),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Call `reload` after the library was first loaded, if that's enabled by
+    /// `Builder::reload_on_initial_load`.
+    fn initial_reload(&mut self) -> Result<(), Error> {
+        if !self.reload_on_initial_load {
+            return Ok(());
+        }
+        let mut error = None;
+        if let Some(ref sym) = self.sym {
+            let state = Self::get_state_ptr(&mut self.state);
+            sym.reload(&mut self.host, state, &mut error);
+        }
+        self.check_state_guards("reload");
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Call `reload` on a state that was saved in a file by an earlier run,
+    /// see `Builder::mmap_state`.
+    fn restore_state(&mut self) -> Result<(), Error> {
+        let mut error = None;
+        if let Some(ref sym) = self.sym {
+            let state = Self::get_state_ptr(&mut self.state);
+            sym.reload(&mut self.host, state, &mut error);
+        }
+        self.check_state_guards("reload");
+        self.write_state_header();
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Call `init` on a library that didn't exist yet when the `Reloadable`
+    /// was created, now that it's loaded.
+    fn deferred_init(&mut self) -> Result<(), Error> {
+        match self.call_init(&()) {
+            Ok(quit) => {
+                self.needs_init = false;
+                if let Ok(path) = self.path.canonicalize() {
+                    self.path = path;
+                }
+                match quit.exit_code() {
+                    None => self.initial_reload(),
+                    Some(code) => Err(Error::QuitRequestedAtInit(code)),
+                }
+            }
+            Err(err) => {
+                // Don't deinit a library that never got initialized
+                self.sym = None;
+                Err(err)
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    fn load(path: &Path, strategy: &LoadingStrategy) -> Result<AppSym<Host>, Error> {
+        let live_path = strategy.live_path(path, "live.dll");
+        strategy.retry_load(|| {
+            copy_library(path, &live_path, strategy)?;
+            AppSym::new(&live_path, strategy)
+        })
+    }
+
+    #[cfg(not(windows))]
+    fn load(path: &Path, strategy: &LoadingStrategy) -> Result<AppSym<Host>, Error> {
+        strategy.retry_load(|| AppSym::new(path, strategy))
+    }
+
+    /// Load the library from a copy, so that it can be loaded while the
+    /// currently loaded library is still open.
+    ///
+    /// Two copies are used in turn, so the copy being loaded is never the one
+    /// that's currently loaded. Once a library was leaked, or while old
+    /// libraries may be kept open, every copy gets a new name instead, since
+    /// the loader would hand back an open library that has the same name. The
+    /// name only changes once a copy is kept, see `next_live_slot`, so a copy
+    /// that's dropped because the reload failed doesn't use up the other name.
+    fn load_copy(&self) -> Result<AppSym<Host>, Error> {
+        load_live_copy(&self.path, self.live_slot, &self.loading_strategy)
+    }
+
+    /// Move on to the next name for copies of the library, once the copy
+    /// loaded from the current one is kept.
+    fn next_live_slot(&mut self) {
+        self.live_slot = if self.leaked || self.previous_policy != PreviousLibraryPolicy::Close {
+            self.live_slot + 1
+        } else {
+            1 - self.live_slot
+        };
+    }
+
+    /// Call the update method on the library.
+    ///
+    /// If no library is currently loaded, this does nothing and returns
+    /// [`ShouldQuit::No`](enum.ShouldQuit.html#).
+    ///
+    /// If `update` reports an error, it's ignored and this returns
+    /// `ShouldQuit::No`. Use [`try_update`][] to get the error instead.
+    ///
+    /// [`try_update`]: struct.Reloadable.html#method.try_update
+    pub fn update(&mut self) -> ShouldQuit {
+        self.try_update().unwrap_or(ShouldQuit::No)
+    }
+
+    /// Call [`update`][] and convert the result with `f`.
+    ///
+    /// This is for fitting `update` into a loop that uses its own type to
+    /// decide whether to keep going, like an event loop's control flow.
+    ///
+    /// ```rust,no_run
+    /// use std::ops::ControlFlow;
+    /// # mod host_api { pub struct Host; }
+    /// # use host_api::Host;
+    ///
+    /// let mut app = live_reload::Reloadable::new("target/debug/libgame.so", Host).unwrap();
+    /// let code = loop {
+    ///     if let ControlFlow::Break(code) = app.update_map(ControlFlow::from) {
+    ///         break code;
+    ///     }
+    /// };
+    /// ```
+    ///
+    /// [`update`]: struct.Reloadable.html#method.update
+    pub fn update_map<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(ShouldQuit) -> R,
+    {
+        f(self.update())
+    }
+
+    /// Reload the library if it changed and then update it, for frameworks
+    /// that want one call per frame.
+    ///
+    /// This calls [`reload`][] and then [`try_update`][], and collects what
+    /// happened into a [`FramePoll`][]. The reload follows the
+    /// [`ReloadErrorPolicy`][] like `reload` does, so with `KeepRunning` a
+    /// failed reload is kept in [`last_error`][] instead of being reported
+    /// here. `update` still runs after a failed reload, on the old library if
+    /// it's still loaded.
+    ///
+    /// ```rust,no_run
+    /// # mod host_api { pub struct Host; }
+    /// # use host_api::Host;
+    /// let mut app = live_reload::Reloadable::new("target/debug/libgame.so", Host).unwrap();
+    /// loop {
+    ///     let frame = app.poll();
+    ///     if frame.reloaded {
+    ///         println!("reloaded the game");
+    ///     }
+    ///     if let Some(err) = frame.error {
+    ///         println!("error: {}", err);
+    ///     }
+    ///     if frame.quit.exit_code().is_some() {
+    ///         break;
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`try_update`]: struct.Reloadable.html#method.try_update
+    /// [`FramePoll`]: struct.FramePoll.html
+    /// [`ReloadErrorPolicy`]: enum.ReloadErrorPolicy.html
+    /// [`last_error`]: struct.Reloadable.html#method.last_error
+    pub fn poll(&mut self) -> FramePoll {
+        let reload_count = self.reload_count;
+        let reload_error = self.reload().err();
+        let reloaded = self.reload_count != reload_count;
+        let (quit, update_error) = match self.try_update() {
+            Ok(quit) => (quit, None),
+            Err(err) => (ShouldQuit::No, Some(err)),
+        };
+        FramePoll {
+            reloaded,
+            quit,
+            error: reload_error.or(update_error),
+            reload_requests: self.take_reload_requests(),
+        }
+    }
+
+    /// Call the update method on the library, returning any error it reports.
+    ///
+    /// This is the same as [`update`][], except that an error reported by the
+    /// library's `update` is returned as [`Error::Library`][].
+    ///
+    /// [`update`]: struct.Reloadable.html#method.update
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    pub fn try_update(&mut self) -> Result<ShouldQuit, Error> {
+        self.call_update(std::ptr::null(), 0)
+    }
+
+    /// Call the update method on the library with a host that's only borrowed
+    /// for this call.
+    ///
+    /// This is for hosts whose services are shared with other threads, for
+    /// example behind an `Arc<Mutex<Host>>`. Lock it and pass in the guard,
+    /// and `host` takes the place of the `Reloadable`'s own host until the
+    /// update returns, including for [`set_before_update`][]. The host the
+    /// `Reloadable` was created with is still used by every other call, like
+    /// `reload` and `deinit`.
+    ///
+    /// Otherwise this works like [`try_update`][].
+    ///
+    /// ```rust,no_run
+    /// use std::sync::{Arc, Mutex};
+    /// use live_reload::Reloadable;
+    /// # #[derive(Default)]
+    /// # struct Host;
+    ///
+    /// let shared = Arc::new(Mutex::new(Host::default()));
+    /// let mut app = Reloadable::new("target/debug/libgame.so", Host::default())
+    ///     .expect("Should successfully load");
+    /// app.update_with_host(&mut shared.lock().unwrap())
+    ///     .expect("Should successfully update");
+    /// ```
+    ///
+    /// [`set_before_update`]: struct.Reloadable.html#method.set_before_update
+    /// [`try_update`]: struct.Reloadable.html#method.try_update
+    pub fn update_with_host(&mut self, host: &mut Host) -> Result<ShouldQuit, Error> {
+        BorrowedHost::new(self, host).reloadable.try_update()
+    }
+
+    /// Call `callback` with the host at the start of every update.
+    ///
+    /// This runs before the library's `update`, even if no library is loaded,
+    /// and is meant for per-frame bookkeeping on the host, like resetting a
+    /// [`ScratchArena`][] that the library allocates from.
+    ///
+    /// [`ScratchArena`]: struct.ScratchArena.html
+    pub fn set_before_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Host) + Send + 'static,
+    {
+        self.before_update = Some(Box::new(callback));
+    }
+
+    /// Ask the library whether it would quit, without updating it.
+    ///
+    /// This calls the library's `peek` function, which only gets shared access
+    /// to the host and the state, so nothing changes. It returns `None` if no
+    /// library is loaded, or if the library doesn't provide a `peek` function
+    /// in its [`live_reload!`][] invocation.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    pub fn peek_quit(&self) -> Option<ShouldQuit> {
+        let peek = self.sym.as_ref()?.rust_api()?.peek?;
+        Some(peek(&self.host, self.state.as_ptr()))
+    }
+
+    /// Call the update method on the library `n` times.
+    ///
+    /// This stops early and returns what the update returned as soon as one of
+    /// them asks to quit, or after one of them reports an error. This is meant
+    /// for headless simulations and tests that need to advance the library by
+    /// many ticks at once.
+    ///
+    /// A library made with [`live_reload!`][] runs the loop itself, so the
+    /// host calls into it only once. The checks that the host does around an
+    /// update, like the state guards and [`set_allocation_check`][], then
+    /// cover all of the updates together. A library with the C ABI is updated
+    /// from a loop in the host instead, and so is every library while there's
+    /// a [`set_before_update`][] callback, since that runs before each update.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`set_allocation_check`]: struct.Reloadable.html#method.set_allocation_check
+    /// [`set_before_update`]: struct.Reloadable.html#method.set_before_update
+    pub fn update_n(&mut self, n: usize) -> ShouldQuit {
+        let update_n = match self.sym.as_ref().and_then(AppSym::rust_api) {
+            Some(api) if self.before_update.is_none() => api.update_n,
+            _ => None,
+        };
+        if let Some(update_n) = update_n {
+            return self
+                .run_update(ShouldQuit::No, |sym, host, state, error| {
+                    update_n(host, state, n, &sym.info, error)
+                })
+                .unwrap_or(ShouldQuit::No);
+        }
+        for _ in 0..n {
+            match self.try_update() {
+                Ok(ShouldQuit::No) => {}
+                Ok(quit) => return quit,
+                Err(_) => break,
+            }
+        }
+        ShouldQuit::No
+    }
+
+    /// Call the update method on the library, passing it a slice of input
+    /// events.
+    ///
+    /// The library has to declare a matching `input` type in its
+    /// [`live_reload!`][] invocation, otherwise this fails with
+    /// [`Error::MismatchedInput`][], unless the `Reloadable` was created with
+    /// [`new_trusting`][]. The events are only borrowed for the duration of
+    /// the call. Calling [`update`][] on such a library passes it an empty
+    /// slice. An error reported by the library's `update` is returned as
+    /// [`Error::Library`][].
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Error::MismatchedInput`]: enum.Error.html#variant.MismatchedInput
+    /// [`new_trusting`]: struct.Reloadable.html#method.new_trusting
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    /// [`update`]: struct.Reloadable.html#method.update
+    pub fn update_with_input<E>(&mut self, events: &[E]) -> Result<ShouldQuit, Error> {
+        if let (Some(ref sym), false) = (&self.sym, self.trusting) {
+            if sym.input_size() != std::mem::size_of::<E>() {
+                return Err(Error::MismatchedInput);
+            }
+        }
+        self.call_update(events.as_ptr() as *const (), events.len())
+    }
+
+    /// Call the library's `update` incrementally, for work that's spread over
+    /// several calls.
+    ///
+    /// This calls the `update_incremental` function the library gave to
+    /// [`live_reload!`][], which returns a [`Progress`][] saying whether it's
+    /// done. The host keeps calling this until it is, and the library keeps
+    /// track of how far it got in its `State`. Since that's part of the state,
+    /// a reload in the middle of the work picks up where it left off. This is
+    /// meant for things like loading screens, where the host keeps drawing
+    /// frames while the library works.
+    ///
+    /// Otherwise this works like [`try_update`][]. If no library is loaded,
+    /// this does nothing and reports that it's done. If the library doesn't
+    /// have an `update_incremental` function, this fails with
+    /// [`Error::MissingFunction`][].
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Progress`]: struct.Progress.html
+    /// [`try_update`]: struct.Reloadable.html#method.try_update
+    /// [`Error::MissingFunction`]: enum.Error.html#variant.MissingFunction
+    pub fn update_incremental(&mut self) -> Result<Progress, Error> {
+        let incremental = match self.sym {
+            Some(ref sym) => sym.rust_api().and_then(|api| api.update_incremental),
+            None => return Ok(Progress { done: true }),
+        };
+        let incremental = incremental.ok_or(Error::MissingFunction("update_incremental"))?;
+        self.run_update(Progress { done: true }, |_, host, state, error| {
+            incremental(host, state, error)
+        })
+    }
+
+    /// Call `update` on the currently loaded library, if there is one.
+    fn call_update(&mut self, input: *const (), input_len: usize) -> Result<ShouldQuit, Error> {
+        self.run_update(ShouldQuit::No, |sym, host, state, error| {
+            sym.update(host, state, input, input_len, error)
+        })
+    }
+
+    /// Call one of the library's update functions through `update`, with the
+    /// checks that go around every update. If no library is loaded, this
+    /// returns `unloaded` instead.
+    fn run_update<R, F>(&mut self, unloaded: R, update: F) -> Result<R, Error>
+    where
+        F: FnOnce(&AppSym<Host>, &mut Host, *mut (), &mut Option<String>) -> R,
+    {
+        if let Some(ref mut callback) = self.before_update {
+            callback(&mut self.host);
+        }
+        let mut error = None;
+        let quit = if let Some(ref sym) = self.sym {
+            #[cfg(feature = "count-allocations")]
+            let before = internals::allocations() + sym.allocations();
+            self.in_update = true;
+            let start = Instant::now();
+            let quit = update(
+                sym,
+                &mut self.host,
+                Self::get_state_ptr(&mut self.state),
+                &mut error,
+            );
+            self.last_update_duration = Some(start.elapsed());
+            self.in_update = false;
+            self.check_state_guards("update");
+            self.reload_requests |= sym.take_reload_requests();
+            #[cfg(feature = "count-allocations")]
+            {
+                let count = internals::allocations() + sym.allocations() - before;
+                match self.allocation_check {
+                    AllocationCheck::Off => {}
+                    AllocationCheck::Count => self.last_update_allocations = Some(count),
+                    AllocationCheck::Panic if count == 0 => {}
+                    AllocationCheck::Panic => {
+                        panic!("update allocated on the heap {} times", count)
+                    }
+                }
+            }
+            quit
+        } else {
+            unloaded
+        };
+        match error {
+            Some(message) => match internals::strip_panic_marker(&message) {
+                Some(message) => {
+                    self.poisoned = true;
+                    Err(Error::Panicked(message.to_owned()))
+                }
+                None => Err(Error::Library(message)),
+            },
+            None => Ok(quit),
+        }
+    }
+
+    /// Reset the `State` and call `init` again, without reloading the library.
+    ///
+    /// This zeroes the state buffer and then calls `init` on the currently
+    /// loaded library, as if the program had just started. `deinit` isn't
+    /// called on the old state first. If no library is currently loaded, this
+    /// only zeroes the state.
+    ///
+    /// This returns whatever `init` returned, so that the host can quit if the
+    /// library can't run anymore.
+    ///
+    /// If the library takes init arguments, use [`reset_with_args`][] instead,
+    /// otherwise this will fail with [`Error::MismatchedInitArgs`][].
+    ///
+    /// Like reloading, this fails with [`Error::ReentrantReload`][] if it's
+    /// called while the library's `update` is running, since the library is
+    /// still using the `State`.
+    ///
+    /// [`reset_with_args`]: struct.Reloadable.html#method.reset_with_args
+    /// [`Error::MismatchedInitArgs`]: enum.Error.html#variant.MismatchedInitArgs
+    /// [`Error::ReentrantReload`]: enum.Error.html#variant.ReentrantReload
+    pub fn reset(&mut self) -> Result<ShouldQuit, Error> {
+        self.reset_with_args(&())
+    }
+
+    /// Reset the `State` and call `init` again with the given init arguments.
+    ///
+    /// See [`reset`][] and [`new_with_args`][] for details.
+    ///
+    /// [`reset`]: struct.Reloadable.html#method.reset
+    /// [`new_with_args`]: struct.Reloadable.html#method.new_with_args
+    pub fn reset_with_args<A>(&mut self, args: &A) -> Result<ShouldQuit, Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        if !self.init_args_match::<A>() {
+            return Err(Error::MismatchedInitArgs);
+        }
+        self.state.zero();
+        self.call_init(args)
+    }
+
+    /// Call `init` on the currently loaded library, if there is one.
+    fn call_init<A>(&mut self, args: &A) -> Result<ShouldQuit, Error> {
+        if !self.init_args_match::<A>() {
+            return Err(Error::MismatchedInitArgs);
+        }
+        let mut error = None;
+        let quit = if let Some(ref sym) = self.sym {
+            sym.init(
+                &mut self.host,
+                Self::get_state_ptr(&mut self.state),
+                args as *const A as *const (),
+                &mut error,
+            )
+        } else {
+            ShouldQuit::No
+        };
+        self.check_state_guards("init");
+        self.write_state_header();
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(quit),
+        }
+    }
+
+    /// Record the currently loaded library's `state_version` in the header in
+    /// front of the `State`.
+    fn write_state_header(&mut self) {
+        if let Some(ref sym) = self.sym {
+            self.state.write_header(u64::from(sym.state_version()));
+        }
+    }
+
+    /// Check that the header in front of the `State` was written for a
+    /// `State` with the layout of `version`, and wasn't overwritten since.
+    fn state_header_matches(&self, version: u32) -> bool {
+        match self.state.header() {
+            // Nothing wrote the header yet, so there's nothing to check
+            Some(StateHeader {
+                magic: 0,
+                version: 0,
+                size: 0,
+            })
+            | None => true,
+            Some(header) => {
+                header.magic == STATE_MAGIC
+                    && header.size == self.state.size as u64
+                    && header.version == u64::from(version)
+            }
+        }
+    }
+
+    /// Whether the library's `Host` type is checked each time it's loaded.
+    ///
+    /// This is true unless the `Reloadable` was created with
+    /// [`new_unchecked`][].
+    ///
+    /// [`new_unchecked`]: struct.Reloadable.html#method.new_unchecked
+    pub fn checks_host(&self) -> bool {
+        self.verify
+    }
+
+    /// Whether the `Reloadable` skips its checks on the library, see
+    /// [`new_trusting`][].
+    ///
+    /// [`new_trusting`]: struct.Reloadable.html#method.new_trusting
+    pub fn is_trusting(&self) -> bool {
+        self.trusting
+    }
+
+    /// Check whether `A` matches the library's init arguments type.
+    fn init_args_match<A>(&self) -> bool {
+        match self.sym {
+            Some(ref sym) => sym.init_args_size() == std::mem::size_of::<A>(),
+            None => true,
+        }
+    }
+
+    /// Preallocate the `State` buffer to fit a state of up to `max_size`
+    /// bytes.
+    ///
+    /// The buffer grows whenever a reload makes the `State` bigger, which
+    /// moves it to a new allocation. Reserving space for the largest state you
+    /// expect up front avoids those reallocations. This never shrinks the
+    /// buffer, but it moves it if it has to grow, which calls the library's
+    /// `on_state_moved` if it has one.
+    pub fn reserve_state(&mut self, max_size: usize) {
+        let old_ptr = self.state.as_ptr();
+        let was_empty = self.state.len() == 0;
+        self.state.reserve(max_size);
+        if was_empty || self.state.as_ptr() == old_ptr {
+            return;
+        }
+        if let Some(ref sym) = self.sym {
+            let state_moved = sym.state_moved();
+            self.state_moved(state_moved, old_ptr as usize);
+        }
+    }
+
+    /// Shrink the `State` buffer to fit the current `State`.
+    ///
+    /// The buffer never shrinks on its own, so after a reload makes the
+    /// `State` smaller, or after [`reserve_state`][], it keeps the memory it
+    /// had. This releases it, which may move the buffer and call the library's
+    /// `on_state_moved`. With [`Builder::stable_state`][] the buffer must not
+    /// move, so this does nothing.
+    ///
+    /// [`reserve_state`]: struct.Reloadable.html#method.reserve_state
+    /// [`Builder::stable_state`]: struct.Builder.html#method.stable_state
+    pub fn compact_state(&mut self) {
+        if self.stable_state {
+            return;
+        }
+        let old_ptr = self.state.as_ptr();
+        let was_empty = self.state.len() == 0;
+        self.state.shrink_to_fit();
+        if was_empty || self.state.as_ptr() == old_ptr {
+            return;
+        }
+        if let Some(ref sym) = self.sym {
+            let state_moved = sym.state_moved();
+            self.state_moved(state_moved, old_ptr as usize);
+        }
+    }
+
+    /// Choose whether the `State` buffer is compacted whenever a reload makes
+    /// the `State` smaller. This is off by default.
+    ///
+    /// See [`compact_state`][].
+    ///
+    /// [`compact_state`]: struct.Reloadable.html#method.compact_state
+    pub fn set_auto_compact_state(&mut self, compact: bool) {
+        self.auto_compact_state = compact;
+    }
+
+    /// How many bytes the `State` buffer currently has allocated.
+    ///
+    /// This is at least the size of the library's `State`, and can be more
+    /// after [`reserve_state`][] or after a reload made the state smaller.
+    ///
+    /// [`reserve_state`]: struct.Reloadable.html#method.reserve_state
+    pub fn state_capacity_bytes(&self) -> usize {
+        self.state.capacity()
+    }
+
+    /// Get the raw bytes of the library's `State`.
+    ///
+    /// Together with [`replace_state_bytes`][], this can snapshot the state
+    /// and restore it later, for example in tests. The bytes are only
+    /// meaningful to a library with the same `State` type, and any pointers in
+    /// the state are copied as they are.
+    ///
+    /// [`replace_state_bytes`]: struct.Reloadable.html#method.replace_state_bytes
+    pub fn state_bytes(&self) -> &[u8] {
+        self.state.bytes()
+    }
+
+    /// Format the library's `State` as a hex dump, for looking at it while
+    /// debugging.
+    ///
+    /// Each line shows the offset into the state, 16 bytes in two groups of
+    /// 8, and those bytes as ASCII with anything unprintable shown as `.`,
+    /// followed by a line with the size of the state:
+    ///
+    /// ```text
+    /// 00000000  2a 00 00 00 00 00 00 00  68 65 6c 6c 6f 00 00 00  |*.......hello...|
+    /// 00000010  01 02                                             |..|
+    /// 00000012
+    /// ```
+    ///
+    /// Comparing the dumps from before and after a reload shows what the
+    /// reload changed. Like [`state_bytes`][], this doesn't need to know the
+    /// `State` type.
+    ///
+    /// [`state_bytes`]: struct.Reloadable.html#method.state_bytes
+    pub fn dump_state(&self) -> String {
+        hex_dump(self.state.bytes())
+    }
+
+    /// Overwrite the library's `State` with raw bytes, usually ones that were
+    /// saved with [`state_bytes`][].
+    ///
+    /// This fails with [`Error::MismatchedStateSize`][] if `bytes` isn't
+    /// exactly the size of the current `State`. None of the library's
+    /// functions are called, so the new state has to be valid as it is.
+    ///
+    /// [`state_bytes`]: struct.Reloadable.html#method.state_bytes
+    /// [`Error::MismatchedStateSize`]: enum.Error.html#variant.MismatchedStateSize
+    pub fn replace_state_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let state = self.state.bytes_mut();
+        if bytes.len() != state.len() {
+            return Err(Error::MismatchedStateSize {
+                expected: state.len(),
+                found: bytes.len(),
+            });
+        }
+        state.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// How many times the library has been reloaded.
+    ///
+    /// This counts every reload that got as far as calling the new library's
+    /// `reload`, including ones where `unload` or `reload` reported an error.
+    /// Reloads don't need an `update` in between, so two changes that arrive
+    /// before the next `update` count twice.
+    pub fn reload_count(&self) -> usize {
+        self.reload_count
+    }
+
+    /// How many times a reload changed the size of the `State` buffer's
+    /// allocation.
+    ///
+    /// The first allocation of the buffer doesn't count. A growing count over
+    /// a session means the `State` keeps getting bigger.
+    pub fn realloc_count(&self) -> usize {
+        self.realloc_count
+    }
+
+    /// Reallocate the buffer used to store the `State`.
+    ///
+    /// This only moves the buffer if it grows past its capacity, which is an
+    /// error if the state is supposed to be stable. If a non-empty state
+    /// moved, this returns its old address.
+    fn realloc_buffer(&mut self, size: usize) -> Result<Option<usize>, Error> {
+        self.check_state_size(size)?;
+        let old_ptr = self.state.as_ptr();
+        let old_capacity = self.state.capacity();
+        let old_len = self.state.len();
+        let was_empty = old_len == 0;
+        self.state.resize(size)?;
+        if self.auto_compact_state && !self.stable_state && self.state.len() < old_len {
+            self.state.shrink_to_fit();
+        }
+        if old_capacity != 0 && self.state.capacity() != old_capacity {
+            self.realloc_count += 1;
+        }
+        if !was_empty && self.state.as_ptr() != old_ptr {
+            Ok(Some(old_ptr as usize))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Check that the buffer can be resized to fit a `State` of `size` bytes.
+    fn check_state_size(&self, size: usize) -> Result<(), Error> {
+        if size > self.max_state_size {
+            return Err(Error::StateTooLarge {
+                max: self.max_state_size,
+                requested: size,
+            });
+        }
+        if self.stable_state && size > self.state.capacity() {
+            return Err(Error::StateWouldMove {
+                capacity: self.state.capacity(),
+                requested: size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Tell the library that its `State` moved away from `old_base`.
+    fn state_moved(
+        &mut self,
+        state_moved: Option<fn(&mut Host, *mut (), usize, usize)>,
+        old_base: usize,
+    ) {
+        let new_base = self.state.as_ptr() as usize;
+        match state_moved {
+            Some(state_moved) => {
+                let state = Self::get_state_ptr(&mut self.state);
+                state_moved(&mut self.host, state, old_base, new_base);
+                self.check_state_guards("on_state_moved");
+            }
+            None => {
+                if let Some(ref mut callback) = self.state_moved_callback {
+                    callback(old_base, new_base);
+                }
+            }
+        }
+    }
+
+    /// Get a void pointer to the `State` buffer.
+    fn get_state_ptr(buffer: &mut StateBuffer) -> *mut () {
+        buffer.as_mut_ptr()
+    }
+
+    /// Panic if the library's `function` wrote outside of the `State`, see
+    /// [`Builder::guard_state`][].
+    ///
+    /// [`Builder::guard_state`]: struct.Builder.html#method.guard_state
+    fn check_state_guards(&self, function: &str) {
+        // Panicking again while unwinding from the first panic would abort
+        if !self.state.guards_intact() && !std::thread::panicking() {
+            panic!(
+                "live-reload: the library's {} wrote outside of its {} byte State",
+                function,
+                self.state.len()
+            );
+        }
+    }
+
+    /// Unload the library without loading it again.
+    ///
+    /// This calls `unload` on the currently loaded library and then unloads the
+    /// dynamic library, leaving the `Reloadable` with no library loaded. The
+    /// state is kept as it is. Until [`reload_now`][] is called, [`update`][]
+    /// does nothing and [`reload`][] ignores changes to the library, so this
+    /// can be used to pause development or to test the unloaded state.
+    ///
+    /// This fails with [`Error::NotLoaded`][] if no library is loaded, and with
+    /// [`Error::ReentrantReload`][] if it's called while the library's `update`
+    /// is running. If the library's `unload` reports an error, the library is
+    /// still unloaded, and the error is returned as [`Error::Library`][].
+    ///
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    /// [`update`]: struct.Reloadable.html#method.update
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`Error::NotLoaded`]: enum.Error.html#variant.NotLoaded
+    /// [`Error::ReentrantReload`]: enum.Error.html#variant.ReentrantReload
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    pub fn unload(&mut self) -> Result<(), Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        if self.sym.is_none() {
+            return Err(Error::NotLoaded);
+        }
+        self.release_resources();
+        let mut error = None;
+        if let (Some(ref sym), false) = (&self.sym, self.poisoned) {
+            let state = Self::get_state_ptr(&mut self.state);
+            sym.unload(&mut self.host, state, &mut error);
+        }
+        self.unload_companions(&mut error);
+        self.check_state_guards("unload");
+        if let Some(ref sym) = self.sym {
+            self.reload_requests |= sym.take_reload_requests();
+        }
+        self.sym = None;
+        self.poisoned = false;
+        for companion in &mut self.companions {
+            companion.sym = None;
+        }
+        self.held_unloaded = true;
+        self.loaded_changed(true);
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Close and reopen another dynamic library around every reload.
+    ///
+    /// This is for a library that loads its own sub-libraries: opening them
+    /// again from the host keeps them tied to the library's reload cycle, so
+    /// that a reload picks up their new versions too. On every reload,
+    /// dependencies are closed after the old library is closed, in the reverse
+    /// order they were added, and reopened in the order they were added before
+    /// the new library is loaded.
+    ///
+    /// The dynamic loader only unloads a library once every handle to it is
+    /// closed, so the library's `unload` has to close its own handles to its
+    /// sub-libraries, and its `reload` should open them again. Opening a path
+    /// that the host already has open gives the library the same copy.
+    ///
+    /// Dependencies aren't watched for changes, only the library itself is.
+    /// If a dependency fails to reopen, the reload fails and leaves no
+    /// library loaded.
+    pub fn add_dependency<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let path = path.canonicalize().map_err(io_context("find", path))?;
+        let library =
+            open_library(&path, &self.loading_strategy).map_err(io_context("load", &path))?;
+        self.dependencies.push(Dependency {
+            path,
+            library: Some(library),
+        });
+        Ok(())
+    }
+
+    /// Reload another library made with [`live_reload!`][] together with the
+    /// library, for a plugin that's split across several libraries that share
+    /// types.
+    ///
+    /// The companion is loaded and its `init` is called right away. From then
+    /// on, a change to the library or to any companion reloads all of them:
+    /// every new library is loaded before any old one is unloaded, so if one
+    /// fails to load, they all keep running as they were. Then `unload` is
+    /// called on the library and then on each companion, in the order they
+    /// were added, the old libraries are all closed, and `reload` is called on
+    /// the library and then on each companion. `deinit` is called on the
+    /// companions after the library's.
+    ///
+    /// A companion has the same `Host` as the library and a `State` of its
+    /// own, which is kept across reloads. Only the library's `update` is
+    /// called, and a companion can't take init arguments, otherwise this fails
+    /// with [`Error::MismatchedInitArgs`][]. Its `State` is limited by
+    /// [`Builder::max_state_size`][] like the library's, and loading a
+    /// companion with a bigger one fails with [`Error::StateTooLarge`][]. It
+    /// isn't covered by [`Builder::stable_state`][], state guards or the
+    /// `state_version` check.
+    ///
+    /// If the [`Builder`][] was given custom [`events`][], they have to report
+    /// changes to the companions too.
+    ///
+    /// [`live_reload!`]: macro.live_reload.html
+    /// [`Error::MismatchedInitArgs`]: enum.Error.html#variant.MismatchedInitArgs
+    /// [`Builder::max_state_size`]: struct.Builder.html#method.max_state_size
+    /// [`Error::StateTooLarge`]: enum.Error.html#variant.StateTooLarge
+    /// [`Builder::stable_state`]: struct.Builder.html#method.stable_state
+    /// [`Builder`]: struct.Builder.html
+    /// [`events`]: struct.Builder.html#method.events
+    pub fn watch_additional_library<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        let path = path.as_ref();
+        let path = path.canonicalize().map_err(io_context("find", path))?;
+        let events = if self.owns_watcher && path.parent() != self.path.parent() {
+            Some(NotifyEvents::new(&path, self.watch_mode)?)
+        } else {
+            None
+        };
+        let mut companion: Companion<Host> = Companion {
+            path,
+            sym: None,
+            state: Vec::new(),
+            live_slot: 0,
+            events,
+        };
+        let sym = companion.load(&self.loading_strategy, self.verify, self.max_state_size)?;
+        companion.state = vec![0; sym.state_size().div_ceil(8)];
+        let mut error = None;
+        let state = companion.state.as_mut_ptr() as *mut ();
+        let quit = sym.init(&mut self.host, state, &() as *const (), &mut error);
+        companion.sym = Some(sym);
+        companion.live_slot = 1;
+        self.companions.push(companion);
+        match (error, quit.exit_code()) {
+            (Some(message), _) => Err(Error::Library(message)),
+            (None, Some(code)) => Err(Error::QuitRequestedAtInit(code)),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Also reload the library when `path` changes.
+    ///
+    /// This is for catching rebuilds whose change to the library file itself
+    /// gets missed, by watching other files that the build writes, like the
+    /// Cargo fingerprint of the library's crate or a file written by a build
+    /// script. A change to any trigger reloads the library as if the library
+    /// had changed, which as with [`reload_now`][] does nothing if the library
+    /// file still has the same contents.
+    ///
+    /// The file doesn't have to exist yet, but its directory does, since it's
+    /// watched for changes to the file. If the [`Builder`][] was given custom
+    /// [`events`][], they have to report changes to the trigger too, and a
+    /// trigger isn't checked when [`set_should_trigger`][] replaced the
+    /// built-in check.
+    ///
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    /// [`Builder`]: struct.Builder.html
+    /// [`events`]: struct.Builder.html#method.events
+    /// [`set_should_trigger`]: struct.Reloadable.html#method.set_should_trigger
+    pub fn add_reload_trigger<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        use std::io::{Error as IoError, ErrorKind};
+
+        let path = std::path::absolute(path.as_ref())?;
+        let (dir, name) = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => (dir.canonicalize().map_err(io_context("find", dir))?, name),
+            _ => return Err(IoError::new(ErrorKind::InvalidInput, "not a file path").into()),
+        };
+        let path = dir.join(name);
+        let events = if self.owns_watcher && Some(dir.as_path()) != self.path.parent() {
+            Some(NotifyEvents::new(&path, self.watch_mode)?)
+        } else {
+            None
+        };
+        self.triggers.push(ReloadTrigger { path, events });
+        Ok(())
+    }
+
+    /// Call `unload` on every loaded companion, keeping the first error.
+    fn unload_companions(&mut self, error: &mut Option<String>) {
+        for companion in &mut self.companions {
+            if let Some(ref sym) = companion.sym {
+                let mut unload_error = None;
+                let state = companion.state.as_mut_ptr() as *mut ();
+                sym.unload(&mut self.host, state, &mut unload_error);
+                *error = error.take().or(unload_error);
+            }
+        }
+    }
+
+    /// Keep the currently loaded library in memory forever, instead of closing
+    /// it when it's replaced.
+    ///
+    /// This is an escape hatch for a host that holds on to function pointers
+    /// or other addresses from the library, which would dangle once the
+    /// library is closed. After this, the library's code and static data stay
+    /// valid until the program exits, even after it's reloaded or the
+    /// `Reloadable` is dropped. Its lifecycle functions are still called as
+    /// usual.
+    ///
+    /// Every leaked library stays mapped for the rest of the program, so
+    /// leaking on every reload grows memory use by the size of the library
+    /// each time. Since the loader would return a leaked library again when
+    /// asked to open the same file, every later reload loads the library from
+    /// a new copy next to it. On Windows, those copies can't be deleted while
+    /// they're loaded, so they're left behind on disk.
+    ///
+    /// This fails with [`Error::NotLoaded`][] if no library is loaded.
+    ///
+    /// [`Error::NotLoaded`]: enum.Error.html#variant.NotLoaded
+    pub fn leak_current_library(&mut self) -> Result<(), Error> {
+        let sym = self.sym.as_mut().ok_or(Error::NotLoaded)?;
+        sym.leak = true;
+        self.leaked = true;
+        Ok(())
+    }
+
+    /// Choose whether to keep the old library open for a while after a
+    /// reload.
+    ///
+    /// Normally the old library is closed as soon as the new one is loaded.
+    /// If something still runs the old library's code after its `unload`, like
+    /// a thread it spawned that's still finishing, that crashes. With
+    /// [`PreviousLibraryPolicy::KeepFor`][], each replaced library stays open
+    /// until that much time has passed, and is closed by the first call to
+    /// [`reload`][] after that. With
+    /// [`PreviousLibraryPolicy::KeepUntilReleased`][], they all stay open until
+    /// [`release_previous`][] is called. Either way, they're closed when the
+    /// `Reloadable` is dropped.
+    ///
+    /// Every library that's kept open takes up its full size in memory, so
+    /// reloading often with a long grace period can add up. While old
+    /// libraries may be kept open, every reload loads the library from a new
+    /// copy next to it, like after [`leak_current_library`][]. On Windows,
+    /// those copies can't be deleted while they're loaded, so they're left
+    /// behind on disk.
+    ///
+    /// Switching back to [`PreviousLibraryPolicy::Close`][] closes every old
+    /// library right away.
+    ///
+    /// [`PreviousLibraryPolicy::KeepFor`]: enum.PreviousLibraryPolicy.html#variant.KeepFor
+    /// [`PreviousLibraryPolicy::KeepUntilReleased`]: enum.PreviousLibraryPolicy.html#variant.KeepUntilReleased
+    /// [`PreviousLibraryPolicy::Close`]: enum.PreviousLibraryPolicy.html#variant.Close
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`release_previous`]: struct.Reloadable.html#method.release_previous
+    /// [`leak_current_library`]: struct.Reloadable.html#method.leak_current_library
+    pub fn set_previous_library_policy(&mut self, policy: PreviousLibraryPolicy) {
+        self.previous_policy = policy;
+        self.release_expired_previous();
+    }
+
+    /// Close every old library that was kept open after a reload, see
+    /// [`set_previous_library_policy`][].
+    ///
+    /// This returns how many libraries were closed.
+    ///
+    /// [`set_previous_library_policy`]: struct.Reloadable.html#method.set_previous_library_policy
+    pub fn release_previous(&mut self) -> usize {
+        let count = self.previous.len();
+        self.previous.clear();
+        count
+    }
+
+    /// Close the old libraries whose grace period is over.
+    fn release_expired_previous(&mut self) {
+        match self.previous_policy {
+            PreviousLibraryPolicy::Close => self.previous.clear(),
+            PreviousLibraryPolicy::KeepFor(grace) => self
+                .previous
+                .retain(|&(_, replaced)| replaced.elapsed() < grace),
+            PreviousLibraryPolicy::KeepUntilReleased => {}
+        }
+    }
+
+    /// Release and reacquire a host resource around every reload.
+    ///
+    /// `release` is called with the host before the old library's `unload`,
+    /// and `acquire` is called after the new library's `reload`. This is for
+    /// resources that the library created through the host but that can't
+    /// outlive the library's code, like GPU objects. Resources are released in
+    /// the order they were registered, and acquired in the same order.
+    ///
+    /// Registering another resource with the same `key` replaces the old one.
+    /// If a reload fails, the resources stay released until the next
+    /// successful reload. [`unload`][] releases them as well.
+    ///
+    /// [`unload`]: struct.Reloadable.html#method.unload
+    pub fn register_reload_resource<K, R, A>(&mut self, key: K, release: R, acquire: A)
+    where
+        K: Into<String>,
+        R: FnMut(&mut Host) + Send + 'static,
+        A: FnMut(&mut Host) + Send + 'static,
+    {
+        let resource = ReloadResource {
+            key: key.into(),
+            release: Box::new(release),
+            acquire: Box::new(acquire),
+        };
+        match self.resources.iter_mut().find(|r| r.key == resource.key) {
+            Some(existing) => *existing = resource,
+            None => self.resources.push(resource),
+        }
+    }
+
+    /// Stop releasing and reacquiring the resource registered with `key`.
+    ///
+    /// This returns whether such a resource was registered.
+    pub fn unregister_reload_resource(&mut self, key: &str) -> bool {
+        let before = self.resources.len();
+        self.resources.retain(|r| r.key != key);
+        self.resources.len() != before
+    }
+
+    fn release_resources(&mut self) {
+        for resource in &mut self.resources {
+            (resource.release)(&mut self.host);
+        }
+    }
+
+    /// Check whether a library is currently loaded.
+    ///
+    /// No library is loaded after [`unload`][], or after a reload failed
+    /// after the old library was unloaded. With
+    /// [`ReloadErrorPolicy::KeepRunning`][] the old library stays loaded when a
+    /// reload fails.
+    ///
+    /// [`unload`]: struct.Reloadable.html#method.unload
+    /// [`ReloadErrorPolicy::KeepRunning`]: enum.ReloadErrorPolicy.html#variant.KeepRunning
+    pub fn is_loaded(&self) -> bool {
+        self.sym.is_some()
+    }
+
+    /// Get when the currently loaded library was built.
+    ///
+    /// This is for showing which build is running, and for noticing a stale
+    /// library that a missed change left loaded. The library's build script
+    /// has to record the time with [`emit_built_at`][], otherwise this is
+    /// `None`. It's also `None` if no library is loaded, and for a library
+    /// that uses the `extern "C"` protocol.
+    ///
+    /// [`emit_built_at`]: fn.emit_built_at.html
+    pub fn plugin_built_at(&self) -> Option<SystemTime> {
+        let built_at = self.sym.as_ref()?.built_at()?;
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(built_at))
+    }
+
+    /// Get the range of addresses `(start, end)` that the current library is
+    /// mapped at.
+    ///
+    /// This is meant for crash handlers: an address inside this range belongs
+    /// to the library, and subtracting `start` gives the offset to look up in
+    /// the library's debug info. The range is for the library that's loaded
+    /// right now, so it changes on every reload. It's `None` if no library is
+    /// loaded, or on platforms where the range can't be found (currently
+    /// anything besides Linux, Android, macOS, iOS and Windows).
+    pub fn loaded_region(&self) -> Option<(usize, usize)> {
+        let sym = self.sym.as_ref()?;
+        library_region(sym.address())
+    }
+
+    /// Get the path that the current library was opened from.
+    ///
+    /// This is the library's own path, unless it was loaded from a copy, like
+    /// on Windows or with [`LoadingStrategy::load_new_first`][], in which case
+    /// it's the copy's path. Logs and crash handlers can use it to name the
+    /// file that's actually mapped. On Unix a copy is removed right after it's
+    /// loaded, so the file may not exist anymore. A library loaded with
+    /// [`LoadingStrategy::map_original`][] has no lasting path, so for that
+    /// this is the path of the library file. It's `None` if no library is
+    /// loaded.
+    ///
+    /// [`LoadingStrategy::load_new_first`]: struct.LoadingStrategy.html#structfield.load_new_first
+    /// [`LoadingStrategy::map_original`]: struct.LoadingStrategy.html#structfield.map_original
+    pub fn loaded_file(&self) -> Option<&Path> {
+        self.sym.as_ref().map(|sym| sym.path.as_path())
+    }
+
+    /// Shut down the library now instead of when the `Reloadable` is dropped.
+    ///
+    /// This calls `deinit` on the library and then unloads it, so the host can
+    /// control when that happens relative to tearing down its own resources.
+    /// Dropping a `Reloadable` does the same thing, but without any way to
+    /// report a problem. If the library has a `finalize` function, it's called
+    /// right before `deinit`, here as well as when dropping.
+    ///
+    /// This fails with [`Error::NotLoaded`][] if no library is loaded, for
+    /// example because the last reload failed, since `deinit` can't be called
+    /// in that case. If the library's `finalize` or `deinit` reports an error,
+    /// `deinit` still runs, and the first error is returned as
+    /// [`Error::Library`][].
+    ///
+    /// [`Error::NotLoaded`]: enum.Error.html#variant.NotLoaded
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        if self.sym.is_none() {
+            return Err(Error::NotLoaded);
+        }
+        self.deinit()
+    }
+
+    /// Shut down the library right away, without giving up the `Reloadable`.
+    ///
+    /// This is for tearing everything down after a fatal error, from a place
+    /// that can't move the `Reloadable` out to call [`shutdown`][]. It calls
+    /// `finalize` and `deinit` like `shutdown` does and unloads the library,
+    /// and then marks the `Reloadable` as dead: [`update`][] does nothing
+    /// anymore, reloading never loads a library again, and dropping it only
+    /// drops the host.
+    ///
+    /// Calling it again does nothing, so `deinit` only ever runs once. It
+    /// fails with [`Error::ReentrantReload`][] if it's called while the
+    /// library's `update` is running, and returns an error from `finalize` or
+    /// `deinit` as [`Error::Library`][].
+    ///
+    /// ```rust,no_run
+    /// # mod host_api { pub struct Host; }
+    /// # use host_api::Host;
+    /// let mut app = live_reload::Reloadable::new("target/debug/libgame.so", Host).unwrap();
+    /// app.force_quit().unwrap();
+    /// // Already dead, so this doesn't call deinit again
+    /// app.force_quit().unwrap();
+    /// assert!(app.is_dead());
+    /// assert!(!app.is_loaded());
+    /// ```
+    ///
+    /// [`shutdown`]: struct.Reloadable.html#method.shutdown
+    /// [`update`]: struct.Reloadable.html#method.update
+    /// [`Error::ReentrantReload`]: enum.Error.html#variant.ReentrantReload
+    /// [`Error::Library`]: enum.Error.html#variant.Library
+    pub fn force_quit(&mut self) -> Result<(), Error> {
+        if self.in_update {
+            return Err(Error::ReentrantReload);
+        }
+        if self.dead {
+            return Ok(());
+        }
+        self.dead = true;
+        let was_loaded = self.sym.is_some();
+        let result = self.deinit();
+        self.loaded_changed(was_loaded);
+        result
+    }
+
+    /// Check whether [`force_quit`][] was called, so that no library will be
+    /// loaded anymore.
+    ///
+    /// [`force_quit`]: struct.Reloadable.html#method.force_quit
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Check whether the library's `update` panicked since it was loaded, so
+    /// that its `unload` will be skipped, see [`reload_now_with`][].
+    ///
+    /// [`reload_now_with`]: struct.Reloadable.html#method.reload_now_with
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Take what the library asked the host to do with [`request_reload`][]
+    /// since the last call, leaving no requests behind.
+    ///
+    /// Requests are collected after every `update` and before the library is
+    /// unloaded, so a request made by a library that was since reloaded isn't
+    /// lost.
+    ///
+    /// [`request_reload`]: fn.request_reload.html
+    pub fn take_reload_requests(&mut self) -> ReloadRequest {
+        std::mem::replace(&mut self.reload_requests, ReloadRequest::NONE)
+    }
+
+    /// Shut down the library and take back the `Host`.
+    ///
+    /// This calls `deinit` on the library and unloads it like dropping the
+    /// `Reloadable` does, and then returns the `Host` instead of dropping it,
+    /// so that host resources like a graphics context can be reused. As with
+    /// dropping, an error from `deinit` is ignored, use [`shutdown`][] to see
+    /// it instead.
+    ///
+    /// [`shutdown`]: struct.Reloadable.html#method.shutdown
+    pub fn into_host(mut self) -> Host {
+        let _ = self.deinit();
+        self.host_taken = true;
+        unsafe { ManuallyDrop::take(&mut self.host) }
+    }
+
+    /// Call `deinit` on the library and unload it, if one is loaded.
+    ///
+    /// Afterwards no library is loaded, so this only ever deinits once.
+    fn deinit(&mut self) -> Result<(), Error> {
+        let mut error = None;
+        if let Some(ref sym) = self.sym {
+            if let Some(finalize) = sym.rust_api().and_then(|api| api.finalize) {
+                let state = Self::get_state_ptr(&mut self.state);
+                finalize(&mut self.host, state, &mut error);
+                self.check_state_guards("finalize");
+            }
+            // Like a reload, a failing finalize doesn't stop the shutdown
+            let mut deinit_error = None;
+            let state = Self::get_state_ptr(&mut self.state);
+            sym.deinit(&mut self.host, state, &mut deinit_error);
+            error = error.or(deinit_error);
+        }
+        self.check_state_guards("deinit");
+        self.sym = None;
+        for companion in &mut self.companions {
+            if let Some(sym) = companion.sym.take() {
+                let mut deinit_error = None;
+                let state = companion.state.as_mut_ptr() as *mut ();
+                sym.deinit(&mut self.host, state, &mut deinit_error);
+                error = error.or(deinit_error);
+            }
+        }
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Also reload the library whenever the process receives `SIGUSR1`.
+    ///
+    /// This is for driving reloads from a deploy script or process manager
+    /// instead of the filesystem watcher. It installs a `SIGUSR1` handler for
+    /// the whole process, replacing any existing one, which only records that
+    /// the signal arrived. The next call to [`reload`][] then reloads the
+    /// library, even if no filesystem change was seen. Every `Reloadable` that
+    /// enabled this reloads once per signal.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    #[cfg(unix)]
+    pub fn reload_on_sigusr1(&mut self) -> Result<(), Error> {
+        self.sigusr1_seen = Some(SIGUSR1_COUNT.load(Ordering::SeqCst));
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_sigusr1 as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()) != 0 {
+                self.sigusr1_seen = None;
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Get how long each phase of the last successful reload took.
+    ///
+    /// This is `None` until the library has been reloaded once.
+    pub fn last_reload_timing(&self) -> Option<ReloadTiming> {
+        self.last_reload_timing
+    }
+
+    /// Call `callback` with the timings after every successful reload.
+    ///
+    /// This is meant for profiling how much a reload costs, for example to
+    /// decide whether a library should be split up. See
+    /// [`last_reload_timing`][] to get the timings without a callback.
+    ///
+    /// [`last_reload_timing`]: struct.Reloadable.html#method.last_reload_timing
+    pub fn set_reload_timing_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&ReloadTiming) + Send + 'static,
+    {
+        self.timing_callback = Some(Box::new(callback));
+    }
+
+    /// Call `callback` whenever a library gets loaded while none was, or the
+    /// loaded library is lost, with whether one is loaded now.
+    ///
+    /// This fires when a reload fails and leaves no library loaded, when the
+    /// next reload succeeds again, and for [`unload`][], but not for reloads
+    /// that replace one loaded library with another, or when shutting down.
+    /// It's meant for things like showing a "build broken" overlay exactly
+    /// while no library is loaded, without checking [`is_loaded`][] every
+    /// frame.
+    ///
+    /// [`unload`]: struct.Reloadable.html#method.unload
+    /// [`is_loaded`]: struct.Reloadable.html#method.is_loaded
+    pub fn set_on_loaded_changed<F>(&mut self, callback: F)
+    where
+        F: FnMut(bool) + Send + 'static,
+    {
+        self.loaded_callback = Some(Box::new(callback));
+    }
+
+    /// Call `callback` when the `State` buffer moved to a new address, and the
+    /// library has no `on_state_moved` function to handle it.
+    ///
+    /// The buffer moves when a reload makes the `State` bigger than the
+    /// buffer's capacity, which leaves any pointers into the state that the
+    /// library stored in it dangling. The callback gets the old and the new
+    /// address of the buffer, so the host can warn about it. See
+    /// [`Builder::stable_state`][] to keep the buffer from moving at all.
+    ///
+    /// [`Builder::stable_state`]: struct.Builder.html#method.stable_state
+    pub fn set_on_state_moved<F>(&mut self, callback: F)
+    where
+        F: FnMut(usize, usize) + Send + 'static,
+    {
+        self.state_moved_callback = Some(Box::new(callback));
+    }
+
+    /// Call the `set_on_loaded_changed` callback if a library is loaded now
+    /// but wasn't before, or the other way around.
+    fn loaded_changed(&mut self, was_loaded: bool) {
+        let loaded = self.sym.is_some();
+        if loaded != was_loaded {
+            if let Some(ref mut callback) = self.loaded_callback {
+                callback(loaded);
+            }
+        }
+    }
+
+    /// Choose what [`update`][] does when it allocates on the heap.
+    ///
+    /// This is for keeping the per-frame loop free of allocations. It counts
+    /// the allocations made during `update` on the current thread, both by the
+    /// library and by the host services it calls. Allocations are only counted
+    /// by a binary that installs a [`CountingAllocator`][] as its global
+    /// allocator, so the host and the library each have to install one to be
+    /// counted.
+    ///
+    /// [`update`]: struct.Reloadable.html#method.update
+    /// [`CountingAllocator`]: struct.CountingAllocator.html
+    #[cfg(feature = "count-allocations")]
+    pub fn set_allocation_check(&mut self, check: AllocationCheck) {
+        self.allocation_check = check;
+    }
+
+    /// Get how many times the last [`update`][] allocated on the heap.
+    ///
+    /// This is `None` unless the allocation check is
+    /// [`AllocationCheck::Count`][], see [`set_allocation_check`][].
+    ///
+    /// [`update`]: struct.Reloadable.html#method.update
+    /// [`AllocationCheck::Count`]: enum.AllocationCheck.html#variant.Count
+    /// [`set_allocation_check`]: struct.Reloadable.html#method.set_allocation_check
+    #[cfg(feature = "count-allocations")]
+    pub fn last_update_allocations(&self) -> Option<usize> {
+        self.last_update_allocations
+    }
+
+    /// Choose what [`reload`][] does when reloading the library fails.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    pub fn set_reload_error_policy(&mut self, policy: ReloadErrorPolicy) {
+        self.error_policy = policy;
+    }
+
+    /// Reload at most once per `interval`.
+    ///
+    /// When the library changes faster than this, for example because a tool
+    /// keeps rewriting it, [`reload`][] skips reloading until `interval` has
+    /// passed since the last reload, and then reloads once to pick up the
+    /// latest version. The events keep being drained in the meantime, this
+    /// only limits how often the library is actually reloaded. [`reload_now`][]
+    /// isn't affected.
+    ///
+    /// The default of zero doesn't limit reloads.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    pub fn set_min_reload_interval(&mut self, interval: Duration) {
+        self.min_reload_interval = interval;
+    }
+
+    /// Don't reload while the file at `path` exists.
+    ///
+    /// This is for build scripts that create a lock file before they start
+    /// writing the library and remove it once it's done. While the lock
+    /// exists, [`reload`][] doesn't reload, but remembers that the library
+    /// changed, and reloads on the first call after the lock is gone. That way
+    /// a half-written library is never loaded. [`reload_now`][] ignores the
+    /// lock.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`reload_now`]: struct.Reloadable.html#method.reload_now
+    pub fn set_build_lock<P: AsRef<Path>>(&mut self, path: P) {
+        self.build_lock = Some(path.as_ref().to_owned());
+    }
+
+    /// Stop checking for a build lock set with [`set_build_lock`][].
+    ///
+    /// [`set_build_lock`]: struct.Reloadable.html#method.set_build_lock
+    pub fn clear_build_lock(&mut self) {
+        self.build_lock = None;
+    }
+
+    /// Get the error from the last failed [`reload`][], if the
+    /// [`ReloadErrorPolicy`][] is `KeepRunning`.
+    ///
+    /// The error stays until a later call to `reload` actually reloads the
+    /// library, or finds that the library file is the loaded library again.
+    /// A call that has no change to reload leaves it in place.
+    ///
+    /// [`reload`]: struct.Reloadable.html#method.reload
+    /// [`ReloadErrorPolicy`]: enum.ReloadErrorPolicy.html
+    pub fn last_error(&self) -> Option<&Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Get all of the counters and status of the `Reloadable` at once.
+    pub fn stats(&self) -> ReloadStats {
+        ReloadStats {
+            reload_count: self.reload_count,
+            realloc_count: self.realloc_count,
+            last_reload: self.last_reload,
+            last_update_duration: self.last_update_duration,
+            last_error: self.last_error.as_ref().map(Error::to_string),
+            is_loaded: self.is_loaded(),
+        }
+    }
+
+    /// Get a reference to the `Host` struct.
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    /// Get a mutable reference to the `Host` struct.
+    ///
+    /// See [`with_host`][] for which changes to the host are safe.
+    ///
+    /// [`with_host`]: struct.Reloadable.html#method.with_host
+    pub fn host_mut(&mut self) -> &mut Host {
+        &mut self.host
+    }
+
+    /// Run `f` with mutable access to the `Host` struct, between calls into the
+    /// library.
+    ///
+    /// Since this borrows the `Reloadable` mutably, it can't run while the
+    /// library is in one of its lifecycle functions, so the library sees
+    /// either the old host or the new one, never a half-updated one. This is
+    /// the place to wrap the host's function pointers, for example to add
+    /// metrics, or to swap out the data they work with.
+    ///
+    /// The library was compiled against the layout of `Host`, so only its
+    /// values may change. Replacing a function pointer with another one of the
+    /// same signature is fine, but anything that changes the type behind a
+    /// pointer the library holds on to isn't. Changing the `Host` type itself
+    /// means building a new `Reloadable` with a library compiled against the
+    /// new type.
+    ///
+    /// This returns whatever `f` returns.
+    pub fn with_host<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Host) -> R,
+    {
+        f(&mut self.host)
+    }
+}
+
+impl<Host> Drop for Reloadable<Host> {
+    fn drop(&mut self) {
+        let _ = self.deinit();
+        if !self.host_taken {
+            unsafe { ManuallyDrop::drop(&mut self.host) };
+        }
+    }
+}
+
+type ThreadJob<Host> = Box<dyn FnOnce(&mut Reloadable<Host>) + Send>;
+
+/// A [`Reloadable`][] that lives on a thread of its own, see
+/// [`Builder::run_on_thread`][].
+///
+/// Each call is sent to the thread and waits for it to finish there. Dropping
+/// the handle drops the `Reloadable` on its thread, which calls the library's
+/// `deinit`, and waits for the thread to stop. If the `Reloadable` panics on
+/// its thread, the panic is passed on to the next call on the handle.
+///
+/// [`Reloadable`]: struct.Reloadable.html
+/// [`Builder::run_on_thread`]: struct.Builder.html#method.run_on_thread
+pub struct ThreadHandle<Host> {
+    jobs: Option<std::sync::mpsc::Sender<ThreadJob<Host>>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl<Host> ThreadHandle<Host> {
+    /// Call `f` with the `Reloadable` on its thread, and return the result.
+    ///
+    /// Every method of the `Reloadable` can be called like this, the other
+    /// methods of the handle are shortcuts for the common ones.
+    pub fn run<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Reloadable<Host>) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result, receiver) = channel();
+        if let Some(ref jobs) = self.jobs {
+            let _ = jobs.send(Box::new(move |app: &mut Reloadable<Host>| {
+                let _ = result.send(f(app));
+            }));
+        }
+        match receiver.recv() {
+            Ok(result) => result,
+            Err(_) => self.resume_panic(),
+        }
+    }
+
+    /// Call [`Reloadable::update`][] on the thread.
+    ///
+    /// [`Reloadable::update`]: struct.Reloadable.html#method.update
+    pub fn update(&mut self) -> ShouldQuit {
+        self.run(|app| app.update())
+    }
+
+    /// Call [`Reloadable::try_update`][] on the thread.
+    ///
+    /// [`Reloadable::try_update`]: struct.Reloadable.html#method.try_update
+    pub fn try_update(&mut self) -> Result<ShouldQuit, Error> {
+        self.run(|app| app.try_update())
+    }
+
+    /// Call [`Reloadable::reload`][] on the thread.
+    ///
+    /// [`Reloadable::reload`]: struct.Reloadable.html#method.reload
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.run(|app| app.reload())
+    }
+
+    /// Shut down the library on the thread and stop the thread, see
+    /// [`Reloadable::shutdown`][].
+    ///
+    /// [`Reloadable::shutdown`]: struct.Reloadable.html#method.shutdown
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.run(|app| {
+            if !app.is_loaded() {
+                return Err(Error::NotLoaded);
+            }
+            app.force_quit()
+        })
+    }
+
+    /// Wait for the thread, which stopped without answering, and pass on its
+    /// panic.
+    fn resume_panic(&mut self) -> ! {
+        self.jobs = None;
+        match self.thread.take().map(std::thread::JoinHandle::join) {
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            _ => panic!("the Reloadable's thread stopped"),
+        }
+    }
+}
+
+impl<Host> Drop for ThreadHandle<Host> {
+    fn drop(&mut self) {
+        // Closing the channel stops the thread
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A library linked into the host program, driven like a [`Reloadable`][].
+///
+/// This is for shipping builds, where the library doesn't need to be reloaded
+/// anymore. Instead of loading a dynamic library, it calls the lifecycle
+/// functions in a `RELOAD_API` that's linked in directly. Build the library
+/// with `crate-type = ["cdylib", "rlib"]` and add it as a normal dependency of
+/// the host, then pass the `RELOAD_API` that [`live_reload!`][] defined in it
+/// to [`new`][]:
+///
+/// ```rust,ignore
+/// #[cfg(debug_assertions)]
+/// let mut app = live_reload::Reloadable::new("target/debug/libgame.so", host)?;
+/// #[cfg(not(debug_assertions))]
+/// let mut app = live_reload::StaticReloadable::new(&game::RELOAD_API, host)?;
+/// ```
+///
+/// The rest of the API works the same as on a `Reloadable`, except that
+/// reloading does nothing.
+///
+/// [`Reloadable`]: struct.Reloadable.html
+/// [`live_reload!`]: macro.live_reload.html
+/// [`new`]: struct.StaticReloadable.html#method.new
+pub struct StaticReloadable<Host: 'static> {
+    api: &'static internals::ReloadApi<Host>,
+    state: StateBuffer,
+    host: ManuallyDrop<Host>,
+    host_taken: bool,
+    initialized: bool,
+    /// Passed to the library's `init` and `update`.
+    info: PluginInfo,
+}
+
+impl<Host: 'static> StaticReloadable<Host> {
+    /// Create the state and call `init` on the library.
+    ///
+    /// See [`Reloadable::new`][].
+    ///
+    /// [`Reloadable::new`]: struct.Reloadable.html#method.new
+    pub fn new(api: &'static internals::ReloadApi<Host>, host: Host) -> Result<Self, Error> {
+        Self::new_with_args(api, host, &())
+    }
+
+    /// Create the state and call `init` on the library with the given init
+    /// arguments.
+    ///
+    /// See [`Reloadable::new_with_args`][].
+    ///
+    /// [`Reloadable::new_with_args`]: struct.Reloadable.html#method.new_with_args
+    pub fn new_with_args<A>(
+        api: &'static internals::ReloadApi<Host>,
+        host: Host,
+        args: &A,
+    ) -> Result<Self, Error> {
+        let mut app = StaticReloadable {
+            api,
+            state: StateBuffer::new(StateAlignment::Word, false),
+            host: ManuallyDrop::new(host),
+            host_taken: false,
+            initialized: false,
+            info: std::env::current_exe()
+                .map(|exe| PluginInfo::for_library(&exe))
+                .unwrap_or_default(),
+        };
+        app.state.resize((api.size)())?;
+        match app.reset_with_args(args)? {
+            ShouldQuit::No => {
+                app.initialized = true;
+                Ok(app)
+            }
+            quit => {
+                // Dropping the app will deinit the library
+                app.initialized = true;
+                Err(Error::QuitRequestedAtInit(quit.exit_code().unwrap_or(0)))
+            }
+        }
+    }
+
+    /// Do nothing, since a linked library can't change.
+    ///
+    /// This exists so that a host can use the same main loop with either type.
+    pub fn reload(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Call the update method on the library.
+    ///
+    /// See [`Reloadable::update`][].
+    ///
+    /// [`Reloadable::update`]: struct.Reloadable.html#method.update
+    pub fn update(&mut self) -> ShouldQuit {
+        self.try_update().unwrap_or(ShouldQuit::No)
+    }
+
+    /// Call the update method on the library and convert the result with `f`.
+    ///
+    /// See [`Reloadable::update_map`][].
+    ///
+    /// [`Reloadable::update_map`]: struct.Reloadable.html#method.update_map
+    pub fn update_map<R, F>(&mut self, f: F) -> R
+    where
+        F: FnOnce(ShouldQuit) -> R,
+    {
+        f(self.update())
+    }
+
+    /// Call the update method on the library, returning any error it reports.
+    ///
+    /// See [`Reloadable::try_update`][].
+    ///
+    /// [`Reloadable::try_update`]: struct.Reloadable.html#method.try_update
+    pub fn try_update(&mut self) -> Result<ShouldQuit, Error> {
+        self.call_update(std::ptr::null(), 0)
+    }
+
+    /// Call the update method on the library, passing it a slice of input
+    /// events.
+    ///
+    /// See [`Reloadable::update_with_input`][].
+    ///
+    /// [`Reloadable::update_with_input`]: struct.Reloadable.html#method.update_with_input
+    /// [`PluginInfo`]: struct.PluginInfo.html
+    pub fn update_with_input<E>(&mut self, events: &[E]) -> Result<ShouldQuit, Error> {
+        if self.api.input_size != std::mem::size_of::<E>() {
+            return Err(Error::MismatchedInput);
+        }
+        self.call_update(events.as_ptr() as *const (), events.len())
+    }
+
+    fn call_update(&mut self, input: *const (), input_len: usize) -> Result<ShouldQuit, Error> {
+        let mut error = None;
+        let state = self.state.as_mut_ptr();
+        let quit = (self.api.update)(
+            &mut self.host,
+            state,
+            input,
+            input_len,
+            &self.info,
+            &mut error,
+        );
+        match error {
+            Some(message) => match internals::strip_panic_marker(&message) {
+                Some(message) => Err(Error::Panicked(message.to_owned())),
+                None => Err(Error::Library(message)),
+            },
+            None => Ok(quit),
+        }
+    }
+
+    /// Call the library's `update` incrementally.
+    ///
+    /// See [`Reloadable::update_incremental`][].
+    ///
+    /// [`Reloadable::update_incremental`]: struct.Reloadable.html#method.update_incremental
+    pub fn update_incremental(&mut self) -> Result<Progress, Error> {
+        let incremental = self
+            .api
+            .update_incremental
+            .ok_or(Error::MissingFunction("update_incremental"))?;
+        let mut error = None;
+        let progress = incremental(&mut self.host, self.state.as_mut_ptr(), &mut error);
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(progress),
+        }
+    }
+
+    /// Ask the library whether it would quit, without updating it.
+    ///
+    /// See [`Reloadable::peek_quit`][].
+    ///
+    /// [`Reloadable::peek_quit`]: struct.Reloadable.html#method.peek_quit
+    pub fn peek_quit(&self) -> Option<ShouldQuit> {
+        let peek = self.api.peek?;
+        Some(peek(&self.host, self.state.as_ptr()))
+    }
+
+    /// Take what the library asked the host to do since the last call.
+    ///
+    /// See [`Reloadable::take_reload_requests`][].
+    ///
+    /// [`Reloadable::take_reload_requests`]: struct.Reloadable.html#method.take_reload_requests
+    pub fn take_reload_requests(&mut self) -> ReloadRequest {
+        ReloadRequest((self.api.take_reload_requests)())
+    }
+
+    /// Reset the `State` and call `init` again.
+    ///
+    /// See [`Reloadable::reset`][].
+    ///
+    /// [`Reloadable::reset`]: struct.Reloadable.html#method.reset
+    pub fn reset(&mut self) -> Result<ShouldQuit, Error> {
+        self.reset_with_args(&())
+    }
+
+    /// Reset the `State` and call `init` again with the given init arguments.
+    ///
+    /// See [`Reloadable::reset_with_args`][].
+    ///
+    /// [`Reloadable::reset_with_args`]: struct.Reloadable.html#method.reset_with_args
+    pub fn reset_with_args<A>(&mut self, args: &A) -> Result<ShouldQuit, Error> {
+        if self.api.init_args_size != std::mem::size_of::<A>() {
+            return Err(Error::MismatchedInitArgs);
+        }
+        self.state.zero();
+        let mut error = None;
+        let state = self.state.as_mut_ptr();
+        let args = args as *const A as *const ();
+        let quit = (self.api.init)(&mut self.host, state, args, &self.info, &mut error);
+        match error {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(quit),
+        }
+    }
+
+    /// Call `deinit` on the library now instead of when this is dropped.
+    ///
+    /// See [`Reloadable::shutdown`][].
+    ///
+    /// [`Reloadable::shutdown`]: struct.Reloadable.html#method.shutdown
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.deinit()
+    }
+
+    /// Call `deinit` on the library and take back the `Host`.
+    ///
+    /// See [`Reloadable::into_host`][].
+    ///
+    /// [`Reloadable::into_host`]: struct.Reloadable.html#method.into_host
+    pub fn into_host(mut self) -> Host {
+        let _ = self.deinit();
+        self.host_taken = true;
+        unsafe { ManuallyDrop::take(&mut self.host) }
+    }
+
+    fn deinit(&mut self) -> Result<(), Error> {
+        if !std::mem::replace(&mut self.initialized, false) {
+            return Ok(());
+        }
+        let mut error = None;
+        if let Some(finalize) = self.api.finalize {
+            finalize(&mut self.host, self.state.as_mut_ptr(), &mut error);
+        }
+        let mut deinit_error = None;
+        (self.api.deinit)(&mut self.host, self.state.as_mut_ptr(), &mut deinit_error);
+        match error.or(deinit_error) {
+            Some(message) => Err(Error::Library(message)),
+            None => Ok(()),
+        }
+    }
+
+    /// Get a reference to the `Host` struct.
+    pub fn host(&self) -> &Host {
+        &self.host
+    }
+
+    /// Get a mutable reference to the `Host` struct.
+    pub fn host_mut(&mut self) -> &mut Host {
+        &mut self.host
+    }
+}
+
+impl<Host: 'static> Drop for StaticReloadable<Host> {
+    fn drop(&mut self) {
+        let _ = self.deinit();
+        if !self.host_taken {
+            unsafe { ManuallyDrop::drop(&mut self.host) };
+        }
+    }
+}
+
+/// Should the main program quit? More self-documenting than a boolean!
+///
+/// This type is returned by the [`update`][] method, since with a boolean it's
+/// often unclear if `true` means "should continue" or "should quit".
+///
+/// # ABI
+///
+/// A `ShouldQuit` is returned across the boundary between separately compiled
+/// host and library, so its layout is fixed with `#[repr(u8)]`: it starts with
+/// a `u8` tag, which is 0 for `No`, 1 for `Yes` and 2 for `YesWith`, followed
+/// by the exit code for `YesWith`. These values are part of the
+/// [`internals::ABI_VERSION`][] contract and won't change without bumping it.
+///
+/// ```rust
+/// use live_reload::ShouldQuit;
+///
+/// fn tag(quit: &ShouldQuit) -> u8 {
+///     unsafe { *(quit as *const ShouldQuit as *const u8) }
+/// }
+///
+/// assert_eq!(tag(&ShouldQuit::No), 0);
+/// assert_eq!(tag(&ShouldQuit::Yes), 1);
+/// assert_eq!(tag(&ShouldQuit::YesWith(3)), 2);
+/// ```
+///
+/// [`update`]: struct.Reloadable.html#method.update
+/// [`internals::ABI_VERSION`]: internals/constant.ABI_VERSION.html
+#[derive(Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ShouldQuit {
+    /// The wrapped library thinks the main program should continue running.
+    No = 0,
+    /// The wrapped library thinks the main program should quit now.
+    Yes = 1,
+    /// The wrapped library thinks the main program should quit now, and exit
+    /// with the given status code.
+    YesWith(i32),
+}
+
+impl ShouldQuit {
+    /// The status code the main program should exit with, if it should quit.
+    ///
+    /// This is `None` for `No`, 0 for `Yes`, and the given code for `YesWith`.
+    pub fn exit_code(&self) -> Option<i32> {
+        match *self {
+            ShouldQuit::No => None,
+            ShouldQuit::Yes => Some(0),
+            ShouldQuit::YesWith(code) => Some(code),
+        }
+    }
+}
+
+/// Break with the exit code when the library wants to quit, as given by
+/// [`ShouldQuit::exit_code`][], so that the result of `update` can be used
+/// with `?` or in loops built around `ControlFlow`.
+///
+/// ```rust
+/// use std::ops::ControlFlow;
+/// use live_reload::ShouldQuit;
+///
+/// assert_eq!(ControlFlow::from(ShouldQuit::No), ControlFlow::Continue(()));
+/// assert_eq!(ControlFlow::from(ShouldQuit::Yes), ControlFlow::Break(0));
+/// assert_eq!(ControlFlow::from(ShouldQuit::YesWith(3)), ControlFlow::Break(3));
+/// ```
+///
+/// [`ShouldQuit::exit_code`]: enum.ShouldQuit.html#method.exit_code
+impl From<ShouldQuit> for std::ops::ControlFlow<i32> {
+    fn from(quit: ShouldQuit) -> Self {
+        match quit.exit_code() {
+            Some(code) => std::ops::ControlFlow::Break(code),
+            None => std::ops::ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Helpers for testing that a library reloads correctly.
+///
+/// This module is only available with the `testing` feature.
+#[cfg(feature = "testing")]
+pub mod testing {
+    use super::Reloadable;
+    use std::fmt::Debug;
+
+    /// A `Host` whose services do nothing, for tests and examples that are
+    /// about reloading rather than about what the host provides.
+    ///
+    /// The library has to use this as its `Host` too, which it can when it
+    /// also depends on `live-reload` with the `testing` feature:
+    ///
+    /// ```rust,no_run
+    /// # #[macro_use] extern crate live_reload;
+    /// use live_reload::testing::NullHost;
+    /// use live_reload::ShouldQuit;
+    ///
+    /// struct State {
+    ///     frames: u64,
+    /// }
+    ///
+    /// live_reload! {
+    ///     host: NullHost;
+    ///     state: State;
+    ///     init: my_init;
+    ///     reload: my_reload;
+    ///     update: my_update;
+    ///     unload: my_unload;
+    ///     deinit: my_deinit;
+    /// }
+    ///
+    /// fn my_init(_: &mut NullHost, state: &mut State) {
+    ///     state.frames = 0;
+    /// }
+    ///
+    /// fn my_reload(_: &mut NullHost, _: &mut State) {}
+    ///
+    /// fn my_update(host: &mut NullHost, state: &mut State) -> ShouldQuit {
+    ///     state.frames += 1;
+    ///     (host.print)("this goes nowhere");
+    ///     ShouldQuit::No
+    /// }
+    ///
+    /// fn my_unload(_: &mut NullHost, _: &mut State) {}
+    ///
+    /// fn my_deinit(_: &mut NullHost, _: &mut State) {}
+    /// # fn main() {}
+    /// ```
+    #[derive(Clone, Copy, Debug)]
+    pub struct NullHost {
+        /// Prints a message, like the demo's host. This discards it.
+        pub print: fn(&str),
+    }
+
+    impl Default for NullHost {
+        fn default() -> Self {
+            NullHost { print: |_| {} }
+        }
+    }
+
+    /// Assert that reloading the library keeps the part of its state picked
+    /// out by `project`.
+    ///
+    /// This reads the state as an `S`, records `project(&state)`, reloads the
+    /// library with [`Reloadable::reload_now`][], and then panics if the
+    /// projection of the new state is different. The library is reloaded even
+    /// if its file didn't change. It also panics if the reload fails, or if the
+    /// state isn't the size of `S`.
+    ///
+    /// # Safety
+    ///
+    /// `S` has to be the library's `State` type, both before and after the
+    /// reload.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use live_reload::testing::NullHost;
+    ///
+    /// #[repr(C)]
+    /// struct State {
+    ///     score: u32,
+    /// }
+    ///
+    /// let mut app = live_reload::Reloadable::new("target/debug/libgame.so", NullHost::default()).unwrap();
+    /// unsafe { live_reload::testing::assert_state_preserved(&mut app, |state: &State| state.score) };
+    /// ```
+    ///
+    /// [`Reloadable::reload_now`]: ../struct.Reloadable.html#method.reload_now
+    pub unsafe fn assert_state_preserved<S, T, Host, F>(
+        reloadable: &mut Reloadable<Host>,
+        project: F,
+    ) where
+        T: PartialEq + Debug,
+        F: Fn(&S) -> T,
+    {
+        let before = project(state_as::<S, Host>(reloadable));
+        if let Err(err) = reloadable.reload_unchecked(None) {
+            panic!("reloading the library failed: {}", err);
+        }
+        let after = project(state_as::<S, Host>(reloadable));
+        assert_eq!(before, after, "the state changed across a reload");
+    }
+
+    /// Read the library's state as an `S`, panicking if it doesn't fit.
+    unsafe fn state_as<S, Host>(reloadable: &Reloadable<Host>) -> &S {
+        let bytes = reloadable.state_bytes();
+        assert_eq!(
+            bytes.len(),
+            std::mem::size_of::<S>(),
+            "the state isn't the size of the given type"
+        );
+        let ptr = bytes.as_ptr();
+        assert_eq!(
+            ptr as usize % std::mem::align_of::<S>(),
+            0,
+            "the state isn't aligned for the given type"
+        );
+        &*(ptr as *const S)
+    }
+}
+
+/// Things a library can ask the host to do, besides quitting.
+///
+/// A library asks with [`request_reload`][], and the host picks the requests
+/// up with [`Reloadable::take_reload_requests`][]. Several requests can be
+/// combined with `|`.
+///
+/// [`request_reload`]: fn.request_reload.html
+/// [`Reloadable::take_reload_requests`]: struct.Reloadable.html#method.take_reload_requests
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ReloadRequest(u32);
+
+impl ReloadRequest {
+    /// No requests.
+    pub const NONE: ReloadRequest = ReloadRequest(0);
+    /// Read the data files that the library uses again, because they changed,
+    /// without reloading the library's code.
+    pub const ASSETS: ReloadRequest = ReloadRequest(1);
+
+    /// Check whether every request in `other` is also in `self`.
+    pub fn contains(self, other: ReloadRequest) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Check whether there are no requests.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl std::ops::BitOr for ReloadRequest {
+    type Output = ReloadRequest;
+
+    fn bitor(self, other: ReloadRequest) -> ReloadRequest {
+        ReloadRequest(self.0 | other.0)
+    }
+}
+
+impl std::ops::BitOrAssign for ReloadRequest {
+    fn bitor_assign(&mut self, other: ReloadRequest) {
+        self.0 |= other.0;
+    }
+}
+
+/// How far an incremental update got, returned by a library's
+/// `update_incremental`.
+///
+/// See [`Reloadable::update_incremental`][].
+///
+/// [`Reloadable::update_incremental`]: struct.Reloadable.html#method.update_incremental
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(C)]
+pub struct Progress {
+    /// Whether the work is finished. If it isn't, the host should call
+    /// `update_incremental` again.
+    pub done: bool,
+}
+
+/// Exported for compilation reasons but not useful, only look if you're curious.
+///
+/// This module holds to the `ReloadApi` struct, which is what what is looked up
+/// by the `Reloadable` in order to communicate with the reloadable library. It
+/// needs to be exported in order to avoid forcing the type definition into the
+/// pub symbols of the wrapped library. An instance of `ReloadApi` called
+/// `RELOAD_API` is generated by the [`live_reload!`][] macro.
+///
+/// [`live_reload!`]: ../macro.live_reload.html
+pub mod internals {
+    use std::ffi::CString;
+    use std::fmt;
+    use std::os::raw::{c_char, c_void};
+    use std::sync::Mutex;
+
+    /// The value of [`ReloadApi::magic`][], which identifies a `RELOAD_API`
+    /// symbol as one exported by [`live_reload!`][].
+    ///
+    /// [`ReloadApi::magic`]: struct.ReloadApi.html#structfield.magic
+    /// [`live_reload!`]: ../macro.live_reload.html
+    pub const MAGIC: u64 = u64::from_le_bytes(*b"LIVERELD");
+
+    /// The value of [`ReloadApiC::magic`][], which identifies a `RELOAD_API`
+    /// symbol as one that uses the C ABI.
+    ///
+    /// [`ReloadApiC::magic`]: struct.ReloadApiC.html#structfield.magic
+    pub const MAGIC_C: u64 = u64::from_le_bytes(*b"LIVERELC");
+
+    /// Parse the timestamp set by [`emit_built_at`][], giving 0 if it's
+    /// missing or malformed.
+    ///
+    /// [`emit_built_at`]: ../fn.emit_built_at.html
+    pub const fn parse_built_at(value: Option<&str>) -> u64 {
+        let digits = match value {
+            Some(value) => value.as_bytes(),
+            None => return 0,
+        };
+        let mut built_at = 0u64;
+        let mut i = 0;
+        while i < digits.len() {
+            if !digits[i].is_ascii_digit() {
+                return 0;
+            }
+            built_at = built_at * 10 + (digits[i] - b'0') as u64;
+            i += 1;
+        }
+        built_at
+    }
+
+    /// Take the requests made with [`request_reload`][] in the binary this
+    /// function is compiled into.
+    ///
+    /// [`request_reload`]: ../fn.request_reload.html
+    pub fn take_reload_requests() -> u32 {
+        super::RELOAD_REQUESTS.swap(0, std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// The number of heap allocations made on the current thread so far.
+    ///
+    /// Each library has its own allocator, so this only counts the
+    /// allocations made by the binary this function is compiled into. It's
+    /// always 0 unless the `count-allocations` feature is enabled and the
+    /// binary installed a [`CountingAllocator`][].
+    ///
+    /// [`CountingAllocator`]: ../struct.CountingAllocator.html
+    pub fn allocations() -> usize {
+        #[cfg(feature = "count-allocations")]
+        {
+            super::ALLOCATIONS
+                .try_with(|count| count.get())
+                .unwrap_or(0)
+        }
+        #[cfg(not(feature = "count-allocations"))]
+        {
+            0
+        }
+    }
+
+    /// The version of the `ReloadApi` layout.
+    ///
+    /// This is bumped whenever the layout of `ReloadApi` changes, so that a
+    /// library built against a different version of `live-reload` is rejected
+    /// instead of being called through the wrong function pointers.
+    pub const ABI_VERSION: u32 = 1;
+
+    /// The version of the `ReloadApiC` layout.
+    ///
+    /// Unlike [`ABI_VERSION`][], libraries written in other languages spell
+    /// this out by hand, so it only changes along with a breaking release.
+    /// Version 2 added [`ReloadApiC::take_error`][].
+    ///
+    /// [`ABI_VERSION`]: constant.ABI_VERSION.html
+    /// [`ReloadApiC::take_error`]: struct.ReloadApiC.html#structfield.take_error
+    pub const ABI_VERSION_C: u32 = 2;
+
+    /// Marks an error message written by [`catch_panic`][], so that the host
+    /// can tell a panic from an error that the library returned.
+    ///
+    /// [`catch_panic`]: fn.catch_panic.html
+    const PANIC_MARKER: &str = "\u{1}live-reload panic\u{1}";
+
+    /// Call `f`, and if it panics, write the panic's message into `error` and
+    /// return `unwound` instead of unwinding into the host. A library has its
+    /// own copy of the standard library, so the host can't catch its panics.
+    pub fn catch_panic<T, F>(error: &mut Option<String>, unwound: T, f: F) -> T
+    where
+        F: FnOnce(&mut Option<String>) -> T,
+    {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(error))) {
+            Ok(value) => value,
+            Err(panic) => {
+                let message = if let Some(message) = panic.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = panic.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    "Box<dyn Any>".to_owned()
+                };
+                *error = Some(format!("{}{}", PANIC_MARKER, message));
+                unwound
+            }
+        }
+    }
+
+    /// Get the panic message from an error written by [`catch_panic`][].
+    ///
+    /// [`catch_panic`]: fn.catch_panic.html
+    pub(crate) fn strip_panic_marker(message: &str) -> Option<&str> {
+        message.strip_prefix(PANIC_MARKER)
+    }
+
+    /// The return types allowed for the lifecycle functions.
+    ///
+    /// This lets `init` either return nothing or a [`ShouldQuit`][], and lets
+    /// every lifecycle function return a `Result` instead. An error is written
+    /// to the error slot that the host passed in, and reported by the host as
+    /// [`Error::Library`][].
+    ///
+    /// [`ShouldQuit`]: ../enum.ShouldQuit.html
+    /// [`Error::Library`]: ../enum.Error.html#variant.Library
+    pub trait LifecycleReturn<T> {
+        /// Convert to the value returned through the `ReloadApi`, writing any
+        /// error into `error`.
+        fn report(self, error: &mut Option<String>) -> T;
+    }
+
+    impl LifecycleReturn<()> for () {
+        fn report(self, _: &mut Option<String>) {}
+    }
+
+    impl LifecycleReturn<super::ShouldQuit> for () {
+        fn report(self, _: &mut Option<String>) -> super::ShouldQuit {
+            super::ShouldQuit::No
+        }
+    }
+
+    impl LifecycleReturn<super::ShouldQuit> for super::ShouldQuit {
+        fn report(self, _: &mut Option<String>) -> super::ShouldQuit {
+            self
+        }
+    }
+
+    impl<E: fmt::Display> LifecycleReturn<()> for Result<(), E> {
+        fn report(self, error: &mut Option<String>) {
+            if let Err(err) = self {
+                *error = Some(err.to_string());
+            }
+        }
+    }
+
+    impl<E: fmt::Display> LifecycleReturn<super::ShouldQuit> for Result<(), E> {
+        fn report(self, error: &mut Option<String>) -> super::ShouldQuit {
+            LifecycleReturn::<()>::report(self, error);
+            super::ShouldQuit::No
+        }
+    }
+
+    impl<E: fmt::Display> LifecycleReturn<super::ShouldQuit> for Result<super::ShouldQuit, E> {
+        fn report(self, error: &mut Option<String>) -> super::ShouldQuit {
+            self.unwrap_or_else(|err| {
+                *error = Some(err.to_string());
+                super::ShouldQuit::No
+            })
+        }
+    }
+
+    impl LifecycleReturn<super::Progress> for super::Progress {
+        fn report(self, _: &mut Option<String>) -> super::Progress {
+            self
+        }
+    }
+
+    impl<E: fmt::Display> LifecycleReturn<super::Progress> for Result<super::Progress, E> {
+        fn report(self, error: &mut Option<String>) -> super::Progress {
+            self.unwrap_or_else(|err| {
+                *error = Some(err.to_string());
+                super::Progress { done: true }
+            })
+        }
+    }
+
+    /// The error reported by the last lifecycle function of a [`ReloadApiC`][]
+    /// in the binary this is compiled into, and the one that [`take_c_error`][]
+    /// last handed out, which has to stay alive until the next call.
+    ///
+    /// [`ReloadApiC`]: struct.ReloadApiC.html
+    /// [`take_c_error`]: fn.take_c_error.html
+    static C_ERROR: Mutex<(Option<CString>, Option<CString>)> = Mutex::new((None, None));
+
+    /// Like [`LifecycleReturn::report`][], but for the functions of a
+    /// [`ReloadApiC`][], which have no error slot. The error is kept for the
+    /// host to pick up with [`take_c_error`][] instead.
+    ///
+    /// [`LifecycleReturn::report`]: trait.LifecycleReturn.html#tymethod.report
+    /// [`ReloadApiC`]: struct.ReloadApiC.html
+    /// [`take_c_error`]: fn.take_c_error.html
+    pub fn report_c<T>(result: impl LifecycleReturn<T>) -> T {
+        let mut error = None;
+        let value = result.report(&mut error);
+        let mut slot = C_ERROR.lock().unwrap_or_else(|err| err.into_inner());
+        slot.0 = error.map(|message| {
+            // A C string ends at the first nul
+            CString::new(message.replace('\0', "")).unwrap_or_default()
+        });
+        value
+    }
+
+    /// Take the error reported by the last lifecycle function, for
+    /// [`ReloadApiC::take_error`][].
+    ///
+    /// [`ReloadApiC::take_error`]: struct.ReloadApiC.html#structfield.take_error
+    pub extern "C" fn take_c_error() -> *const c_char {
+        let mut slot = C_ERROR.lock().unwrap_or_else(|err| err.into_inner());
+        slot.1 = slot.0.take();
+        slot.1
+            .as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    }
+
+    type UpdateIncremental<Host> = fn(&mut Host, *mut (), &mut Option<String>) -> super::Progress;
+
+    type UpdateN<Host> =
+        fn(&mut Host, *mut (), usize, &super::PluginInfo, &mut Option<String>) -> super::ShouldQuit;
+
     /// Contains function pointers for all the parts of the reloadable object lifecycle.
+    ///
+    /// Each lifecycle function takes an error slot as its last parameter. The
+    /// host passes in `None`, and the library sets it when the function fails.
     #[repr(C)]
     pub struct ReloadApi<Host> {
+        /// Always [`MAGIC`][]. This must stay the first field, so that it can
+        /// be checked no matter how the rest of the layout changes.
+        ///
+        /// [`MAGIC`]: constant.MAGIC.html
+        pub magic: u64,
+        /// The [`ABI_VERSION`][] the library was built with. This must stay
+        /// the second field.
+        ///
+        /// [`ABI_VERSION`]: constant.ABI_VERSION.html
+        pub abi_version: u32,
+        /// The name of the library's package.
+        pub name: &'static str,
+        /// The size of the `Host` type as the library sees it.
+        pub host_size: usize,
+        /// The alignment of the `Host` type as the library sees it.
+        pub host_align: usize,
         /// Returns the size of the State struct so that the host can allocate
         /// space for it.
         pub size: fn() -> usize,
+        /// The version of the `State` layout, which the library bumps whenever
+        /// an old `State` can't be reused. This is 0 when the library doesn't
+        /// give one.
+        pub state_version: u32,
+        /// The size of the arguments type that `init` expects, so that the
+        /// host can check that it passes the right one. This is 0 when the
+        /// library doesn't take any init arguments.
+        pub init_args_size: usize,
+        /// The size of the input events type that `update` expects, so that
+        /// the host can check that it passes the right one. This is 0 when the
+        /// library doesn't take any input.
+        pub input_size: usize,
+        /// When the library was built, in seconds since the Unix epoch, or 0 if
+        /// it's unknown. See [`emit_built_at`][].
+        ///
+        /// [`emit_built_at`]: ../fn.emit_built_at.html
+        pub built_at: u64,
         /// Initializes the State struct when the program is first started.
-        pub init: fn(&mut Host, *mut ()),
+        ///
+        /// The last parameter points to the init arguments passed to
+        /// [`Reloadable::new_with_args`][], or to `()` when using
+        /// [`Reloadable::new`][].
+        ///
+        /// It returns whether the library wants the host to quit right away.
+        /// The [`PluginInfo`][] is passed to the library's `init`, `reload`
+        /// and `update` if it asks for it.
+        ///
+        /// [`Reloadable::new_with_args`]: ../struct.Reloadable.html#method.new_with_args
+        /// [`Reloadable::new`]: ../struct.Reloadable.html#method.new
+        /// [`PluginInfo`]: ../struct.PluginInfo.html
+        pub init: fn(
+            &mut Host,
+            *mut (),
+            *const (),
+            &super::PluginInfo,
+            &mut Option<String>,
+        ) -> super::ShouldQuit,
         /// Makes any necessary updates when the program is reloaded.
         ///
         /// This will probably be normally empty. If you changed the State
         /// struct since the last compile, then it won't necessarily be
         /// correctly initialized. For safety, you should make your State struct
         /// `#[repr(C)]` and only add members at the end.
-        pub reload: fn(&mut Host, *mut ()),
-        /// Update the
-        pub update: fn(&mut Host, *mut ()) -> super::ShouldQuit,
+        pub reload: fn(&mut Host, *mut (), &super::PluginInfo, &mut Option<String>),
+        /// Update the state, usually once per frame.
+        ///
+        /// The last two parameters are a pointer to and the length of a slice
+        /// of input events, see [`Reloadable::update_with_input`][]. The
+        /// pointer may be null if the length is 0.
+        ///
+        /// [`Reloadable::update_with_input`]: ../struct.Reloadable.html#method.update_with_input
+        pub update: fn(
+            &mut Host,
+            *mut (),
+            *const (),
+            usize,
+            &super::PluginInfo,
+            &mut Option<String>,
+        ) -> super::ShouldQuit,
         /// Prepare for the library to be unloaded before a new version loads.
         ///
         /// This will probably normally be empty except for short periods in
         /// development when you're making lots of live changes and need to do
         /// some kind of migration.
-        pub unload: fn(&mut Host, *mut ()),
+        pub unload: fn(&mut Host, *mut (), &mut Option<String>),
+        /// Do final shutdowns before the program completely quits.
+        pub deinit: fn(&mut Host, *mut (), &mut Option<String>),
+        /// Report whether the library would ask to quit, without changing the
+        /// state. This is `None` when the library doesn't provide it, see
+        /// [`Reloadable::peek_quit`][].
+        ///
+        /// [`Reloadable::peek_quit`]: ../struct.Reloadable.html#method.peek_quit
+        pub peek: Option<fn(&Host, *const ()) -> super::ShouldQuit>,
+        /// Fix up the state after the `State` buffer moved from the first
+        /// address to the second one. This is `None` when the library doesn't
+        /// provide it.
+        pub state_moved: Option<fn(&mut Host, *mut (), usize, usize)>,
+        /// Do part of an update, and report whether it's done. This is `None`
+        /// when the library doesn't provide it, see
+        /// [`Reloadable::update_incremental`][].
+        ///
+        /// [`Reloadable::update_incremental`]: ../struct.Reloadable.html#method.update_incremental
+        pub update_incremental: Option<UpdateIncremental<Host>>,
+        /// Call `update` up to the given number of times with no input,
+        /// stopping after an update that asks to quit or reports an error. It
+        /// returns what the last update returned. [`live_reload!`][] always
+        /// provides it, and the host loops over `update` itself when it's
+        /// `None`, see [`Reloadable::update_n`][].
+        ///
+        /// [`live_reload!`]: ../macro.live_reload.html
+        /// [`Reloadable::update_n`]: ../struct.Reloadable.html#method.update_n
+        pub update_n: Option<UpdateN<Host>>,
+        /// Do any last work on the state, like saving it, right before
+        /// `deinit` when the host shuts down. This is `None` when the library
+        /// doesn't provide it.
+        pub finalize: Option<fn(&mut Host, *mut (), &mut Option<String>)>,
+        /// Check that the library works, before the host switches to it. This
+        /// is `None` when the library doesn't provide it, see
+        /// [`Reloadable::validate_candidate`][].
+        ///
+        /// [`Reloadable::validate_candidate`]: ../struct.Reloadable.html#method.validate_candidate
+        pub self_test: Option<fn(&mut Option<String>)>,
+        /// The library's [`allocations`][], so that the host can count the
+        /// allocations the library makes during `update`.
+        ///
+        /// [`allocations`]: fn.allocations.html
+        pub allocations: fn() -> usize,
+        /// The library's [`take_reload_requests`][], so that the host can see
+        /// what the library asked for.
+        ///
+        /// [`take_reload_requests`]: fn.take_reload_requests.html
+        pub take_reload_requests: fn() -> u32,
+    }
+
+    /// The same lifecycle functions as [`ReloadApi`][], for libraries that
+    /// aren't written in Rust.
+    ///
+    /// Everything here uses the C ABI, so a library in any language can
+    /// export a `RELOAD_API` of this type. In C, it looks like this:
+    ///
+    /// ```c
+    /// typedef struct { uint8_t tag; int32_t code; } ShouldQuit;
+    ///
+    /// typedef struct {
+    ///     uint64_t magic;       /* "LIVERELC", as little-endian bytes */
+    ///     uint32_t abi_version; /* 2 */
+    ///     const char *name;
+    ///     size_t host_size;
+    ///     size_t host_align;
+    ///     size_t state_size;
+    ///     size_t init_args_size;
+    ///     size_t input_size;
+    ///     ShouldQuit (*init)(Host *, State *, const InitArgs *);
+    ///     void (*reload)(Host *, State *);
+    ///     ShouldQuit (*update)(Host *, State *, const Input *, size_t);
+    ///     void (*unload)(Host *, State *);
+    ///     void (*deinit)(Host *, State *);
+    ///     const char *(*take_error)(void);
+    /// } ReloadApiC;
+    /// ```
+    ///
+    /// The tag of `ShouldQuit` is 0 for [`ShouldQuit::No`][], 1 for `Yes`
+    /// and 2 for `YesWith`, which also sets `code`.
+    ///
+    /// The lifecycle functions have no error slot. Instead, the host calls
+    /// `take_error` after each of them, and a message it returns is reported
+    /// as an [`Error::Library`][]. None of the optional functions of
+    /// `ReloadApi` are available. A Rust library can use this too with the
+    /// `extern "C";` form of [`live_reload!`][].
+    ///
+    /// [`ReloadApi`]: struct.ReloadApi.html
+    /// [`ShouldQuit::No`]: ../enum.ShouldQuit.html#variant.No
+    /// [`Error::Library`]: ../enum.Error.html#variant.Library
+    /// [`live_reload!`]: ../macro.live_reload.html
+    #[repr(C)]
+    pub struct ReloadApiC<Host> {
+        /// Always [`MAGIC_C`][]. This must stay the first field.
+        ///
+        /// [`MAGIC_C`]: constant.MAGIC_C.html
+        pub magic: u64,
+        /// The [`ABI_VERSION_C`][] the library was written for. This must stay
+        /// the second field.
+        ///
+        /// [`ABI_VERSION_C`]: constant.ABI_VERSION_C.html
+        pub abi_version: u32,
+        /// The name of the library as a nul-terminated string, or null.
+        pub name: *const c_char,
+        /// The size of the `Host` type as the library sees it.
+        pub host_size: usize,
+        /// The alignment of the `Host` type as the library sees it.
+        pub host_align: usize,
+        /// The size of the State struct, so that the host can allocate space
+        /// for it.
+        pub state_size: usize,
+        /// The size of the arguments type that `init` expects, or 0.
+        pub init_args_size: usize,
+        /// The size of the input events type that `update` expects, or 0.
+        pub input_size: usize,
+        /// Initializes the State struct when the program is first started.
+        pub init: extern "C" fn(*mut Host, *mut c_void, *const c_void) -> super::ShouldQuit,
+        /// Makes any necessary updates when the program is reloaded.
+        pub reload: extern "C" fn(*mut Host, *mut c_void),
+        /// Update the state, with a pointer to and the length of the input
+        /// events.
+        pub update:
+            extern "C" fn(*mut Host, *mut c_void, *const c_void, usize) -> super::ShouldQuit,
+        /// Prepare for the library to be unloaded before a new version loads.
+        pub unload: extern "C" fn(*mut Host, *mut c_void),
         /// Do final shutdowns before the program completely quits.
-        pub deinit: fn(&mut Host, *mut ()),
+        pub deinit: extern "C" fn(*mut Host, *mut c_void),
+        /// Get the error message of the last lifecycle function that was
+        /// called, as a nul-terminated string, or null if it succeeded. The
+        /// string only has to stay valid until the next call into the library.
+        /// This can be null if the library never reports errors.
+        pub take_error: Option<extern "C" fn() -> *const c_char>,
+    }
+
+    // The only pointer is to the static name, which is never written
+    unsafe impl<Host> Sync for ReloadApiC<Host> {}
+
+    /// Get a nul-terminated copy of `name` for [`ReloadApiC::name`][].
+    ///
+    /// [`ReloadApiC::name`]: struct.ReloadApiC.html#structfield.name
+    pub const fn c_name<const N: usize>(name: &str) -> [u8; N] {
+        let bytes = name.as_bytes();
+        let mut out = [0; N];
+        let mut i = 0;
+        while i < bytes.len() && i + 1 < N {
+            out[i] = bytes[i];
+            i += 1;
+        }
+        out
     }
 }
 
 /// Declare the API functions for a live-reloadable library.
 ///
-/// This generates wrappers around higher-level lifecycle functions, and then
-/// exports them in a struct that the reloader can find.
+/// This generates wrappers around higher-level lifecycle functions, and then
+/// exports them in a struct that the reloader can find.
+///
+/// You need to to specify the host API type, define a struct that represents
+/// the state of your program, and then define methods for `init`, `reload`,
+/// `update`, `unload`, and `deinit`. `init` and `deinit` are called at the very
+/// beginning and end of the program, and `reload` and `unload` are called
+/// immediately after and before the library is reloaded. `update` is
+/// called by the wrapping application as needed.
+///
+/// `init` can also return a [`ShouldQuit`][] instead of nothing, to tell the
+/// host to quit right away if the library finds that it can't run.
+///
+/// Any of the lifecycle functions can also return a `Result` around what they
+/// would normally return, with an error type that implements `Display`. The
+/// error's message is passed back to the host, which reports it as
+/// [`Error::Library`][] instead of the library having to panic.
+///
+/// The host checks the size of `Host` when it loads the library, but that
+/// only catches a mismatch at runtime. To catch it at compile time instead,
+/// keep the expected size as a constant next to `Host` in the shared module,
+/// and add it as `host_size` after the `host` type. Whichever side is rebuilt
+/// after `Host` changed then fails to compile until the constant is updated.
+///
+/// If a change to `State` means that an old state can't be reused at all, you
+/// can add a `state_version` after the `state` type, and bump it along with
+/// that change. The host keeps the version in a header in front of the state,
+/// and when a reload brings in a different version, it resets the state and
+/// calls the new library's `init` instead of `reload`.
+///
+/// If `init` needs some configuration that's only known when the host starts,
+/// you can add an `init_args` type after the `state` type. Your `init` then
+/// takes a third `&InitArgs` parameter, and the host has to pass a value of
+/// that type to [`Reloadable::new_with_args`][].
+///
+/// Similarly, if `update` should receive input events from the host, you can
+/// add an `input` type after that. Your `update` then takes a third
+/// `&[Input]` parameter, filled by [`Reloadable::update_with_input`][].
+///
+/// If the library needs to know where it was loaded from, for example to find
+/// files that are kept next to it, you can add `info: PluginInfo;` after that.
+/// Your `init`, `reload` and `update` then take a [`PluginInfo`][] as their
+/// last parameter.
+///
+/// Finally, you can add a `peek` function after `deinit`, which takes the host
+/// and state by shared reference and returns whether `update` would quit. The
+/// host can call it with [`Reloadable::peek_quit`][] without advancing the
+/// library.
+///
+/// After that, you can add an `on_state_moved` function, which is called with
+/// the old and new address of the state whenever the host has to move it to a
+/// bigger buffer. If your state stores pointers into itself, or anything else
+/// derived from its own address, this is where to fix them up. When a reload
+/// moves the state, it's called on the new library right before its `reload`.
+///
+/// Then, for work that takes longer than one frame, you can add an
+/// `update_incremental` function. It takes the host and state like `update`,
+/// but returns a [`Progress`][] saying whether it's done, and the host calls it
+/// with [`Reloadable::update_incremental`][] until it is.
+///
+/// Then, you can add a `finalize` function, which is called right before
+/// `deinit` when the host shuts down, with the same signature. Where `deinit`
+/// tears things down, this is for the last step that should still see
+/// everything working, like saving the game.
+///
+/// Last of all, you can add a `self_test` function, which takes nothing and
+/// returns nothing or a `Result`. The host calls it from
+/// [`Reloadable::validate_candidate`][] to check a new build before switching
+/// to it, so it should check whatever the library can check without a host
+/// or state, and report an error if something's wrong.
+///
+/// Starting with `extern "C";` exports an [`internals::ReloadApiC`][]
+/// instead, the table that libraries in other languages export. This is
+/// mostly useful to test a host against that protocol from Rust. It takes
+/// everything up to `deinit`, and errors from the lifecycle functions are
+/// passed back through [`ReloadApiC::take_error`][].
+///
+/// # Example
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State {}
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// # use live_reload::ShouldQuit;
+/// # fn my_update(_: &mut Host, _: &mut State) -> ShouldQuit { ShouldQuit::No }
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With init arguments:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State { width: u32 }
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// # use live_reload::ShouldQuit;
+/// # fn my_update(_: &mut Host, _: &mut State) -> ShouldQuit { ShouldQuit::No }
+/// pub struct Config {
+///     width: u32,
+/// }
+///
+/// fn my_init(_: &mut Host, state: &mut State, config: &Config) {
+///     state.width = config.width;
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init_args: Config;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With input events:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State { x: i32 }
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// use live_reload::ShouldQuit;
+///
+/// pub enum Input {
+///     Left,
+///     Right,
+///     Quit,
+/// }
+///
+/// fn my_update(_: &mut Host, state: &mut State, input: &[Input]) -> ShouldQuit {
+///     for event in input {
+///         match *event {
+///             Input::Left => state.x -= 1,
+///             Input::Right => state.x += 1,
+///             Input::Quit => return ShouldQuit::Yes,
+///         }
+///     }
+///     ShouldQuit::No
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     input: Input;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With the plugin info:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State { level: u32 }
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// use live_reload::PluginInfo;
+///
+/// // The level to start at is kept in a file next to the library
+/// fn load_level(info: &PluginInfo) -> u32 {
+///     info.plugin_dir()
+///         .and_then(|dir| std::fs::read_to_string(dir.join("level.txt")).ok())
+///         .and_then(|level| level.trim().parse().ok())
+///         .unwrap_or(1)
+/// }
+///
+/// fn my_init(_: &mut Host, state: &mut State, info: &PluginInfo) {
+///     state.level = load_level(info);
+/// }
+///
+/// fn my_reload(_: &mut Host, _: &mut State, _: &PluginInfo) {}
+///
+/// fn my_update(_: &mut Host, _: &mut State, _: &PluginInfo) {}
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     info: PluginInfo;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With a `peek` function:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State { lives: u32 }
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// use live_reload::ShouldQuit;
+///
+/// fn my_peek(_: &Host, state: &State) -> ShouldQuit {
+///     if state.lives == 0 {
+///         ShouldQuit::Yes
+///     } else {
+///         ShouldQuit::No
+///     }
+/// }
+///
+/// fn my_update(host: &mut Host, state: &mut State) -> ShouldQuit {
+///     my_peek(host, state)
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+///     peek: my_peek;
+/// }
+/// ```
+///
+/// With an `on_state_moved` function:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// #[repr(C)]
+/// struct State {
+///     names: [u8; 64],
+///     // Points somewhere into `names`
+///     current: *const u8,
+/// }
+///
+/// fn my_state_moved(_: &mut Host, state: &mut State, old_base: usize, new_base: usize) {
+///     if !state.current.is_null() {
+///         state.current = (state.current as usize - old_base + new_base) as *const u8;
+///     }
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+///     on_state_moved: my_state_moved;
+/// }
+/// ```
+///
+/// With an `update_incremental` function:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// use live_reload::Progress;
+///
+/// #[repr(C)]
+/// struct State {
+///     chunks_loaded: u32,
+/// }
+///
+/// fn my_load_level(_: &mut Host, state: &mut State) -> Progress {
+///     state.chunks_loaded += 1;
+///     Progress {
+///         done: state.chunks_loaded == 64,
+///     }
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+///     update_incremental: my_load_level;
+/// }
+/// ```
+///
+/// With a `finalize` function:
 ///
-/// You need to to specify the host API type, define a struct that represents
-/// the state of your program, and then define methods for `init`, `reload`,
-/// `update`, `unload`, and `deinit`. `init` and `deinit` are called at the very
-/// beginning and end of the program, and `reload` and `unload` are called
-/// immediately after and before the library is loaded/reloaded. `update` is
-/// called by the wrapping application as needed.
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # mod host_api { pub struct Host { pub save: fn(u32) } }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// #[repr(C)]
+/// struct State {
+///     level: u32,
+/// }
 ///
-/// # Example
+/// fn my_save(host: &mut Host, state: &mut State) {
+///     (host.save)(state.level);
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+///     finalize: my_save;
+/// }
+/// ```
+///
+/// With a `self_test` function:
 ///
 /// ```rust
 /// # #[macro_use] extern crate live_reload;
@@ -528,11 +7275,144 @@ pub mod internals {
 /// # use host_api::Host;
 /// # fn my_init(_: &mut Host, _: &mut State) {}
 /// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// const LEVELS: &[&str] = &["intro", "caves"];
+///
+/// fn my_self_test() -> Result<(), String> {
+///     if LEVELS.is_empty() {
+///         return Err("there are no levels".to_owned());
+///     }
+///     Ok(())
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+///     self_test: my_self_test;
+/// }
+/// ```
+///
+/// With a `host_size` check:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State {}
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// mod host_api {
+///     pub const HOST_SIZE: usize = 2 * std::mem::size_of::<fn()>();
+///
+///     pub struct Host {
+///         pub print: fn(&str),
+///         pub quit: fn(),
+///     }
+/// }
+/// use host_api::Host;
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     host_size: host_api::HOST_SIZE;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With a `state_version`:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// #[repr(C)]
+/// struct State {
+///     // Was a `[u8; 16]` in version 1
+///     name: [u16; 16],
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     state_version: 2;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With errors:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State { level: u32 }
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_unload(_: &mut Host, _: &mut State) {}
+/// # fn my_deinit(_: &mut Host, _: &mut State) {}
+/// use live_reload::ShouldQuit;
+///
+/// fn my_reload(_: &mut Host, state: &mut State) -> Result<(), String> {
+///     if state.level > 10 {
+///         return Err(format!("level {} doesn't exist anymore", state.level));
+///     }
+///     Ok(())
+/// }
+///
+/// fn my_update(_: &mut Host, state: &mut State) -> Result<ShouldQuit, String> {
+///     state.level += 1;
+///     Ok(ShouldQuit::No)
+/// }
+///
+/// live_reload! {
+///     host: host_api::Host;
+///     state: State;
+///     init: my_init;
+///     reload: my_reload;
+///     update: my_update;
+///     unload: my_unload;
+///     deinit: my_deinit;
+/// }
+/// ```
+///
+/// With the C ABI:
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # #[repr(C)] struct State {}
+/// # mod host_api { pub struct Host; }
+/// # use host_api::Host;
+/// # fn my_init(_: &mut Host, _: &mut State) {}
+/// # fn my_reload(_: &mut Host, _: &mut State) {}
+/// # fn my_update(_: &mut Host, _: &mut State) {}
 /// # fn my_unload(_: &mut Host, _: &mut State) {}
 /// # fn my_deinit(_: &mut Host, _: &mut State) {}
-/// # use live_reload::ShouldQuit;
-/// # fn my_update(_: &mut Host, _: &mut State) -> ShouldQuit { ShouldQuit::No }
 /// live_reload! {
+///     extern "C";
 ///     host: host_api::Host;
 ///     state: State;
 ///     init: my_init;
@@ -542,48 +7422,560 @@ pub mod internals {
 ///     deinit: my_deinit;
 /// }
 /// ```
+///
+/// [`ShouldQuit`]: enum.ShouldQuit.html
+/// [`Error::Library`]: enum.Error.html#variant.Library
+/// [`Reloadable::new_with_args`]: struct.Reloadable.html#method.new_with_args
+/// [`Reloadable::update_with_input`]: struct.Reloadable.html#method.update_with_input
+/// [`PluginInfo`]: struct.PluginInfo.html
+/// [`Reloadable::peek_quit`]: struct.Reloadable.html#method.peek_quit
+/// [`Progress`]: struct.Progress.html
+/// [`Reloadable::update_incremental`]: struct.Reloadable.html#method.update_incremental
+/// [`Reloadable::validate_candidate`]: struct.Reloadable.html#method.validate_candidate
+/// [`internals::ReloadApiC`]: internals/struct.ReloadApiC.html
+/// [`ReloadApiC::take_error`]: internals/struct.ReloadApiC.html#structfield.take_error
 #[macro_export]
 macro_rules! live_reload {
     (host: $Host:ty;
+     $(host_size: $host_size:expr;)?
      state: $State:ty;
+     $(state_version: $state_version:expr;)?
+     $(init_args: $Args:ty;)?
+     $(input: $Input:ty;)?
+     $(info: $Info:ty;)?
      init: $init:ident;
      reload: $reload:ident;
      update: $update:ident;
      unload: $unload:ident;
-     deinit: $deinit:ident;) => {
+     deinit: $deinit:ident;
+     $(peek: $peek:ident;)?
+     $(on_state_moved: $moved:ident;)?
+     $(update_incremental: $incremental:ident;)?
+     $(finalize: $finalize:ident;)?
+     $(self_test: $self_test:ident;)?) => {
         fn cast<'a>(raw_state: *mut ()) -> &'a mut $State {
             unsafe { &mut *(raw_state as *mut $State) }
         }
 
-        fn init_wrapper(host: &mut $Host, raw_state: *mut ()) {
-            $init(host, cast(raw_state))
+        // Check that the lifecycle functions take exactly `$State`, so the
+        // state can't silently be reinterpreted as some other type through a
+        // coercion.
+        const _: () = {
+            let _: fn(&mut $Host, &mut $State $(, &$Args)? $(, &$Info)?) -> _ = $init;
+            let _: fn(&mut $Host, &mut $State $(, &$Info)?) -> _ = $reload;
+            let _: fn(&mut $Host, &mut $State $(, &[$Input])? $(, &$Info)?) -> _ = $update;
+            let _: fn(&mut $Host, &mut $State) -> _ = $unload;
+            let _: fn(&mut $Host, &mut $State) -> _ = $deinit;
+            $(let _: fn(&$Host, &$State) -> ::live_reload::ShouldQuit = $peek;)?
+            $(let _: fn(&mut $Host, &mut $State, usize, usize) = $moved;)?
+            $(let _: fn(&mut $Host, &mut $State) -> _ = $incremental;)?
+            $(let _: fn(&mut $Host, &mut $State) -> _ = $finalize;)?
+            $(let _: fn() -> _ = $self_test;)?
+        };
+
+        $(
+            const _: () = assert!(
+                ::std::mem::size_of::<$Host>() == $host_size,
+                "the size of the Host type doesn't match its host_size",
+            );
+        )?
+
+        fn init_wrapper(
+            host: &mut $Host,
+            raw_state: *mut (),
+            raw_args: *const (),
+            info: &::live_reload::PluginInfo,
+            error: &mut Option<String>,
+        ) -> ::live_reload::ShouldQuit {
+            let _ = (raw_args, info);
+            ::live_reload::internals::LifecycleReturn::report(
+                $init(
+                    host,
+                    cast(raw_state)
+                    $(, unsafe { &*(raw_args as *const $Args) })?
+                    $(, { let info: &$Info = info; info })?
+                ),
+                error,
+            )
+        }
+
+        fn reload_wrapper(
+            host: &mut $Host,
+            raw_state: *mut (),
+            info: &::live_reload::PluginInfo,
+            error: &mut Option<String>,
+        ) {
+            let _ = info;
+            ::live_reload::internals::LifecycleReturn::report(
+                $reload(host, cast(raw_state) $(, { let info: &$Info = info; info })?),
+                error,
+            )
         }
 
-        fn reload_wrapper(host: &mut $Host, raw_state: *mut ()) {
-            $reload(host, cast(raw_state))
+        fn update_wrapper(
+            host: &mut $Host,
+            raw_state: *mut (),
+            raw_input: *const (),
+            input_len: usize,
+            info: &::live_reload::PluginInfo,
+            error: &mut Option<String>,
+        ) -> ::live_reload::ShouldQuit {
+            let _ = (raw_input, input_len, info);
+            ::live_reload::internals::catch_panic(error, ::live_reload::ShouldQuit::No, |error| {
+                ::live_reload::internals::LifecycleReturn::report(
+                    $update(
+                        host,
+                        cast(raw_state)
+                        $(, {
+                            let input: &[$Input] = if input_len == 0 {
+                                &[]
+                            } else {
+                                unsafe { ::std::slice::from_raw_parts(raw_input as *const $Input, input_len) }
+                            };
+                            input
+                        })?
+                        $(, { let info: &$Info = info; info })?
+                    ),
+                    error,
+                )
+            })
         }
 
-        fn update_wrapper(host: &mut $Host, raw_state: *mut ()) -> ::live_reload::ShouldQuit {
-            $update(host, cast(raw_state))
+        fn update_n_wrapper(
+            host: &mut $Host,
+            raw_state: *mut (),
+            n: usize,
+            info: &::live_reload::PluginInfo,
+            error: &mut Option<String>,
+        ) -> ::live_reload::ShouldQuit {
+            for _ in 0..n {
+                let quit = update_wrapper(host, raw_state, ::std::ptr::null(), 0, info, error);
+                if quit != ::live_reload::ShouldQuit::No || error.is_some() {
+                    return quit;
+                }
+            }
+            ::live_reload::ShouldQuit::No
         }
 
-        fn unload_wrapper(host: &mut $Host, raw_state: *mut ()) {
-            $unload(host, cast(raw_state))
+        fn unload_wrapper(host: &mut $Host, raw_state: *mut (), error: &mut Option<String>) {
+            ::live_reload::internals::LifecycleReturn::report($unload(host, cast(raw_state)), error)
         }
 
-        fn deinit_wrapper(host: &mut $Host, raw_state: *mut ()) {
-            $deinit(host, cast(raw_state))
+        fn deinit_wrapper(host: &mut $Host, raw_state: *mut (), error: &mut Option<String>) {
+            ::live_reload::internals::LifecycleReturn::report($deinit(host, cast(raw_state)), error)
         }
 
+        $(
+            fn peek_wrapper(host: &$Host, raw_state: *const ()) -> ::live_reload::ShouldQuit {
+                $peek(host, unsafe { &*(raw_state as *const $State) })
+            }
+        )?
+
+        $(
+            fn state_moved_wrapper(host: &mut $Host, raw_state: *mut (), old: usize, new: usize) {
+                $moved(host, cast(raw_state), old, new)
+            }
+        )?
+
+        $(
+            fn update_incremental_wrapper(
+                host: &mut $Host,
+                raw_state: *mut (),
+                error: &mut Option<String>,
+            ) -> ::live_reload::Progress {
+                ::live_reload::internals::LifecycleReturn::report($incremental(host, cast(raw_state)), error)
+            }
+        )?
+
+        $(
+            fn finalize_wrapper(host: &mut $Host, raw_state: *mut (), error: &mut Option<String>) {
+                ::live_reload::internals::LifecycleReturn::report($finalize(host, cast(raw_state)), error)
+            }
+        )?
+
+        $(
+            fn self_test_wrapper(error: &mut Option<String>) {
+                ::live_reload::internals::LifecycleReturn::report($self_test(), error)
+            }
+        )?
+
         #[no_mangle]
         pub static RELOAD_API: ::live_reload::internals::ReloadApi<$Host> =
             ::live_reload::internals::ReloadApi {
+                magic: ::live_reload::internals::MAGIC,
+                abi_version: ::live_reload::internals::ABI_VERSION,
+                name: match option_env!("CARGO_PKG_NAME") {
+                    Some(name) => name,
+                    None => "",
+                },
+                host_size: ::std::mem::size_of::<$Host>(),
+                host_align: ::std::mem::align_of::<$Host>(),
                 size: ::std::mem::size_of::<$State>,
+                state_version: ::live_reload::__live_reload_or!({$($state_version)?} else {0}),
+                init_args_size: ::std::mem::size_of::<
+                    ::live_reload::__live_reload_or!({$($Args)?} else {()}),
+                >(),
+                input_size: ::std::mem::size_of::<
+                    ::live_reload::__live_reload_or!({$($Input)?} else {()}),
+                >(),
+                built_at: ::live_reload::internals::parse_built_at(option_env!(
+                    "LIVE_RELOAD_BUILT_AT"
+                )),
+                init: init_wrapper,
+                reload: reload_wrapper,
+                update: update_wrapper,
+                unload: unload_wrapper,
+                deinit: deinit_wrapper,
+                peek: ::live_reload::__live_reload_or!(
+                    {$({
+                        let _ = $peek;
+                        Some(peek_wrapper)
+                    })?}
+                    else {None}
+                ),
+                state_moved: ::live_reload::__live_reload_or!(
+                    {$({
+                        let _ = $moved;
+                        Some(state_moved_wrapper)
+                    })?}
+                    else {None}
+                ),
+                update_incremental: ::live_reload::__live_reload_or!(
+                    {$({
+                        let _ = $incremental;
+                        Some(update_incremental_wrapper)
+                    })?}
+                    else {None}
+                ),
+                update_n: Some(update_n_wrapper),
+                finalize: ::live_reload::__live_reload_or!(
+                    {$({
+                        let _ = $finalize;
+                        Some(finalize_wrapper)
+                    })?}
+                    else {None}
+                ),
+                self_test: ::live_reload::__live_reload_or!(
+                    {$({
+                        let _ = $self_test;
+                        Some(self_test_wrapper)
+                    })?}
+                    else {None}
+                ),
+                allocations: ::live_reload::internals::allocations,
+                take_reload_requests: ::live_reload::internals::take_reload_requests,
+            };
+    };
+    (extern "C";
+     host: $Host:ty;
+     $(host_size: $host_size:expr;)?
+     state: $State:ty;
+     $(init_args: $Args:ty;)?
+     $(input: $Input:ty;)?
+     init: $init:ident;
+     reload: $reload:ident;
+     update: $update:ident;
+     unload: $unload:ident;
+     deinit: $deinit:ident;) => {
+        fn cast<'a>(raw_state: *mut ::std::os::raw::c_void) -> &'a mut $State {
+            unsafe { &mut *(raw_state as *mut $State) }
+        }
+
+        fn cast_host<'a>(host: *mut $Host) -> &'a mut $Host {
+            unsafe { &mut *host }
+        }
+
+        const _: () = {
+            let _: ::live_reload::__live_reload_or!(
+                {$(fn(&mut $Host, &mut $State, &$Args) -> _)?}
+                else {fn(&mut $Host, &mut $State) -> _}
+            ) = $init;
+            let _: fn(&mut $Host, &mut $State) -> _ = $reload;
+            let _: ::live_reload::__live_reload_or!(
+                {$(fn(&mut $Host, &mut $State, &[$Input]) -> _)?}
+                else {fn(&mut $Host, &mut $State) -> _}
+            ) = $update;
+            let _: fn(&mut $Host, &mut $State) -> _ = $unload;
+            let _: fn(&mut $Host, &mut $State) -> _ = $deinit;
+        };
+
+        $(
+            const _: () = assert!(
+                ::std::mem::size_of::<$Host>() == $host_size,
+                "the size of the Host type doesn't match its host_size",
+            );
+        )?
+
+        extern "C" fn init_wrapper(
+            raw_host: *mut $Host,
+            raw_state: *mut ::std::os::raw::c_void,
+            raw_args: *const ::std::os::raw::c_void,
+        ) -> ::live_reload::ShouldQuit {
+            ::live_reload::internals::report_c(
+                ::live_reload::__live_reload_or! {
+                    {$($init(cast_host(raw_host), cast(raw_state), unsafe { &*(raw_args as *const $Args) }))?}
+                    else {{
+                        let _ = raw_args;
+                        $init(cast_host(raw_host), cast(raw_state))
+                    }}
+                },
+            )
+        }
+
+        extern "C" fn reload_wrapper(raw_host: *mut $Host, raw_state: *mut ::std::os::raw::c_void) {
+            ::live_reload::internals::report_c($reload(cast_host(raw_host), cast(raw_state)))
+        }
+
+        extern "C" fn update_wrapper(
+            raw_host: *mut $Host,
+            raw_state: *mut ::std::os::raw::c_void,
+            raw_input: *const ::std::os::raw::c_void,
+            input_len: usize,
+        ) -> ::live_reload::ShouldQuit {
+            ::live_reload::internals::report_c(
+                ::live_reload::__live_reload_or! {
+                    {$({
+                        let input: &[$Input] = if input_len == 0 {
+                            &[]
+                        } else {
+                            unsafe { ::std::slice::from_raw_parts(raw_input as *const $Input, input_len) }
+                        };
+                        $update(cast_host(raw_host), cast(raw_state), input)
+                    })?}
+                    else {{
+                        let _ = (raw_input, input_len);
+                        $update(cast_host(raw_host), cast(raw_state))
+                    }}
+                },
+            )
+        }
+
+        extern "C" fn unload_wrapper(raw_host: *mut $Host, raw_state: *mut ::std::os::raw::c_void) {
+            ::live_reload::internals::report_c($unload(cast_host(raw_host), cast(raw_state)))
+        }
+
+        extern "C" fn deinit_wrapper(raw_host: *mut $Host, raw_state: *mut ::std::os::raw::c_void) {
+            ::live_reload::internals::report_c($deinit(cast_host(raw_host), cast(raw_state)))
+        }
+
+        const RELOAD_API_PKG: &str = match option_env!("CARGO_PKG_NAME") {
+            Some(name) => name,
+            None => "",
+        };
+        static RELOAD_API_NAME: [u8; RELOAD_API_PKG.len() + 1] =
+            ::live_reload::internals::c_name(RELOAD_API_PKG);
+
+        #[no_mangle]
+        pub static RELOAD_API: ::live_reload::internals::ReloadApiC<$Host> =
+            ::live_reload::internals::ReloadApiC {
+                magic: ::live_reload::internals::MAGIC_C,
+                abi_version: ::live_reload::internals::ABI_VERSION_C,
+                name: RELOAD_API_NAME.as_ptr() as *const ::std::os::raw::c_char,
+                host_size: ::std::mem::size_of::<$Host>(),
+                host_align: ::std::mem::align_of::<$Host>(),
+                state_size: ::std::mem::size_of::<$State>(),
+                init_args_size: ::std::mem::size_of::<
+                    ::live_reload::__live_reload_or!({$($Args)?} else {()}),
+                >(),
+                input_size: ::std::mem::size_of::<
+                    ::live_reload::__live_reload_or!({$($Input)?} else {()}),
+                >(),
                 init: init_wrapper,
                 reload: reload_wrapper,
                 update: update_wrapper,
                 unload: unload_wrapper,
                 deinit: deinit_wrapper,
+                take_error: Some(::live_reload::internals::take_c_error),
             };
     };
 }
+
+/// Declare a live-reloadable library that only has an `update` function.
+///
+/// This is the smallest possible setup, for trying things out. It works like
+/// [`live_reload!`][], but `init`, `reload`, `unload` and `deinit` all do
+/// nothing, so only `update` has to be written. Since `init` doesn't do
+/// anything, the state starts out zeroed, so it should be a type that's
+/// valid when all of its bytes are zero. Switch to `live_reload!` once you
+/// need any of the other functions.
+///
+/// ```rust
+/// # #[macro_use] extern crate live_reload;
+/// # fn main() {}
+/// # mod host_api { pub struct Host { pub print: fn(&str) } }
+/// use host_api::Host;
+/// use live_reload::ShouldQuit;
+///
+/// struct State {
+///     frames: u64,
+/// }
+///
+/// fn update(host: &mut Host, state: &mut State) -> ShouldQuit {
+///     state.frames += 1;
+///     (host.print)(&format!("Frame {}", state.frames));
+///     ShouldQuit::No
+/// }
+///
+/// live_reload_simple! {
+///     host: Host;
+///     state: State;
+///     update: update;
+/// }
+/// ```
+///
+/// [`live_reload!`]: macro.live_reload.html
+#[macro_export]
+macro_rules! live_reload_simple {
+    (host: $Host:ty;
+     state: $State:ty;
+     update: $update:ident;) => {
+        fn __live_reload_nothing(_: &mut $Host, _: &mut $State) {}
+
+        ::live_reload::live_reload! {
+            host: $Host;
+            state: $State;
+            init: __live_reload_nothing;
+            reload: __live_reload_nothing;
+            update: $update;
+            unload: __live_reload_nothing;
+            deinit: __live_reload_nothing;
+        }
+    };
+}
+
+/// Declare a `Host` field that lets the library call a closure owned by the
+/// host.
+///
+/// A `Host` can only hold plain function pointers, since a closure's type
+/// isn't known to the library. This declares a struct holding a type-erased
+/// pointer to a boxed closure along with a function that calls it, so that a
+/// host service can capture things like a logger handle. The closure stays
+/// owned by the host, and is dropped along with the struct.
+///
+/// Like the rest of the `Host`, the struct should be declared in the module
+/// shared by the host and the library. The host creates it with `new`, and the
+/// library calls it with `call`. The closure has to be `Send`, so the struct
+/// is `Send` as well.
+///
+/// # Example
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate live_reload;
+///
+/// mod host_api {
+///     host_callback! {
+///         /// Log a message in the host's log.
+///         pub struct Log(message: &str);
+///     }
+///
+///     pub struct Host {
+///         pub log: Log,
+///     }
+/// }
+/// use host_api::{Host, Log};
+///
+/// fn main() {
+///     use std::sync::{Arc, Mutex};
+///
+///     let lines = Arc::new(Mutex::new(Vec::new()));
+///     let log_lines = lines.clone();
+///     let mut host = Host {
+///         log: Log::new(move |message: &str| log_lines.lock().unwrap().push(message.to_owned())),
+///     };
+///
+///     // In the library:
+///     host.log.call("hello");
+///     assert_eq!(*lines.lock().unwrap(), ["hello"]);
+/// }
+/// ```
+#[macro_export]
+macro_rules! host_callback {
+    ($(#[$attr:meta])*
+     $vis:vis struct $Name:ident($($arg:ident: $Arg:ty),* $(,)?) $(-> $Ret:ty)?;) => {
+        $(#[$attr])*
+        $vis struct $Name {
+            context: *mut (),
+            call: unsafe fn(*mut () $(, $Arg)*) $(-> $Ret)?,
+            drop: unsafe fn(*mut ()),
+        }
+
+        impl $Name {
+            /// Wrap a closure so that it can be called by the library.
+            $vis fn new<F>(closure: F) -> Self
+            where
+                F: FnMut($($Arg),*) $(-> $Ret)? + Send + 'static,
+            {
+                unsafe fn call<F: FnMut($($Arg),*) $(-> $Ret)?>(
+                    context: *mut () $(, $arg: $Arg)*
+                ) $(-> $Ret)? {
+                    (*(context as *mut F))($($arg),*)
+                }
+                unsafe fn drop<F>(context: *mut ()) {
+                    ::std::mem::drop(::std::boxed::Box::from_raw(context as *mut F));
+                }
+                $Name {
+                    context: ::std::boxed::Box::into_raw(::std::boxed::Box::new(closure)) as *mut (),
+                    call: call::<F>,
+                    drop: drop::<F>,
+                }
+            }
+
+            /// Call the closure.
+            $vis fn call(&mut self $(, $arg: $Arg)*) $(-> $Ret)? {
+                unsafe { (self.call)(self.context $(, $arg)*) }
+            }
+        }
+
+        impl Drop for $Name {
+            fn drop(&mut self) {
+                unsafe { (self.drop)(self.context) }
+            }
+        }
+
+        // The closure is required to be `Send`
+        unsafe impl Send for $Name {}
+    };
+}
+
+/// Expands to the first group of tokens if it's non-empty, otherwise to the
+/// second one. Used by [`live_reload!`][] for its optional parts.
+///
+/// [`live_reload!`]: macro.live_reload.html
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __live_reload_or {
+    ({} else {$($default:tt)*}) => { $($default)* };
+    ({$($given:tt)+} else {$($default:tt)*}) => { $($given)+ };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::same_path;
+    use std::path::Path;
+
+    #[test]
+    fn same_path_matches_identical_paths() {
+        let path = Path::new("/target/debug/libfoo.dylib");
+        assert!(same_path(path, path));
+        assert!(!same_path(path, Path::new("/target/debug/libbar.dylib")));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "macos", target_os = "ios", windows))]
+    fn same_path_ignores_case_on_case_insensitive_platforms() {
+        assert!(same_path(
+            Path::new("/target/debug/LibFoo.dylib"),
+            Path::new("/target/debug/libfoo.dylib"),
+        ));
+    }
+
+    #[test]
+    #[cfg(not(any(target_os = "macos", target_os = "ios", windows)))]
+    fn same_path_is_case_sensitive_elsewhere() {
+        assert!(!same_path(
+            Path::new("/target/debug/LibFoo.dylib"),
+            Path::new("/target/debug/libfoo.dylib"),
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+//! Helpers shared by the integration tests, most of which load the demo
+//! library or the test plugin.
+
+// Each test binary uses a different part of this module
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+#[path = "../../demo/src/shared_api.rs"]
+mod shared_api;
+
+pub use self::shared_api::Host;
+
+thread_local! {
+    static OUTPUT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+fn record(msg: &str) {
+    OUTPUT.with(|output| output.borrow_mut().push_str(msg));
+}
+
+/// A host that records what the library prints on this thread, see
+/// `take_output`.
+pub fn host() -> Host {
+    Host { print: record }
+}
+
+/// Take everything the library printed on this thread so far.
+pub fn take_output() -> String {
+    OUTPUT.with(|output| std::mem::take(&mut *output.borrow_mut()))
+}
+
+/// Build the library from `package` if needed, and get its path.
+///
+/// The library is built into the same target directory and profile as the
+/// test itself, since `cargo test` doesn't build a cdylib for other tests to
+/// use.
+fn build_library(package: &str, name: &str) -> PathBuf {
+    static BUILT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    let exe = std::env::current_exe().unwrap();
+    let profile_dir = exe.parent().unwrap().parent().unwrap();
+    let mut built = BUILT.lock().unwrap();
+    if !built.iter().any(|built| built == package) {
+        let mut build = Command::new(env!("CARGO"));
+        build
+            .args(["build", "--quiet", "-p", package, "--lib", "--target-dir"])
+            .arg(profile_dir.parent().unwrap())
+            .current_dir(env!("CARGO_MANIFEST_DIR"));
+        if profile_dir.ends_with("release") {
+            build.arg("--release");
+        }
+        assert!(
+            build.status().unwrap().success(),
+            "building {} failed",
+            package
+        );
+        built.push(package.to_string());
+    }
+    profile_dir.join(live_reload::library_file_name(name))
+}
+
+/// Build the demo library if needed, and get its path.
+pub fn demo_library() -> PathBuf {
+    build_library("reload-demo", "reloadable")
+}
+
+/// Build the test plugin in `tests/plugin` if needed, and get its path.
+pub fn plugin_library() -> PathBuf {
+    build_library("reload-test-plugin", "plugin")
+}
+
+/// Make an empty directory for a test named `name`.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("live-reload-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Put a fresh copy of the demo library in `dir` as if it was the `build`th
+/// build, and get its path.
+///
+/// Each build gets different contents, so that reloading it isn't skipped as
+/// unchanged. The copy is renamed into place like a linker would, so a library
+/// that's already loaded from there isn't overwritten while it's mapped.
+pub fn install_demo(dir: &Path, build: usize) -> PathBuf {
+    install(&demo_library(), dir, "reloadable", build)
+}
+
+/// Put a fresh copy of the test plugin in `dir`, like `install_demo`.
+pub fn install_plugin(dir: &Path, build: usize) -> PathBuf {
+    install(&plugin_library(), dir, "plugin", build)
+}
+
+fn install(library: &Path, dir: &Path, name: &str, build: usize) -> PathBuf {
+    let path = dir.join(live_reload::library_file_name(name));
+    let temp = dir.join(format!("{}.tmp", name));
+    let mut bytes = fs::read(library).unwrap();
+    // The loader ignores anything after the end of the library
+    bytes.extend_from_slice(format!("build {}", build).as_bytes());
+    fs::write(&temp, bytes).unwrap();
+    fs::rename(&temp, &path).unwrap();
+    path
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use std::fs;
+
+use live_reload::{Error, Reloadable};
+
+#[test]
+fn rlib_is_not_a_dynamic_library() {
+    let dir = common::temp_dir("rlib");
+    let path = dir.join("libgame.rlib");
+    fs::write(
+        &path,
+        b"!<arch>\n/               0           0     0     0       8       `\n",
+    )
+    .unwrap();
+
+    match Reloadable::new(&path, common::host()) {
+        Err(Error::NotADynamicLibrary(error_path)) => assert_eq!(error_path, path),
+        Err(err) => panic!("expected NotADynamicLibrary, got {}", err),
+        Ok(_) => panic!("an rlib was loaded"),
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// The lines of this process's memory map that mention `name`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn mappings_of(name: &str) -> Vec<String> {
+    fs::read_to_string("/proc/self/maps")
+        .unwrap()
+        .lines()
+        .filter(|line| line.contains(name))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn map_original_loads_the_build_without_copying_it() {
+    use std::sync::mpsc::channel;
+
+    use live_reload::{DebouncedEvent, LoadingStrategy};
+
+    let dir = common::temp_dir("map-original");
+    let path = common::install_demo(&dir, 0);
+    let (tx, rx) = channel();
+    let strategy = LoadingStrategy {
+        load_new_first: true,
+        map_original: true,
+        ..LoadingStrategy::default()
+    };
+    let mut app = Reloadable::builder(&path, common::host())
+        .loading_strategy(strategy)
+        .events(rx)
+        .build()
+        .unwrap();
+
+    // Reloading with load_new_first would otherwise load the new build from a copy
+    let path = common::install_demo(&dir, 1);
+    tx.send(DebouncedEvent::Write(path.clone())).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_count(), 1);
+    let path = fs::canonicalize(&path).unwrap();
+    assert_eq!(app.loaded_file(), Some(path.as_path()));
+    let mapped = mappings_of(&format!("{}/", dir.canonicalize().unwrap().display()));
+    assert!(!mapped.is_empty());
+    assert!(
+        mapped
+            .iter()
+            .all(|line| line.ends_with(&*path.to_string_lossy())),
+        "{:#?}",
+        mapped
+    );
+    assert!(mappings_of("memfd:libreloadable").is_empty());
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn map_original_copies_a_build_that_is_still_loaded() {
+    use live_reload::{LoadingStrategy, ReloadOutcome};
+
+    /// The input that makes the test plugin panic.
+    const PLUGIN_PANIC: u32 = 1;
+
+    let dir = common::temp_dir("map-original-loaded");
+    let path = common::install_plugin(&dir, 0);
+    let strategy = LoadingStrategy {
+        load_new_first: true,
+        map_original: true,
+        ..LoadingStrategy::default()
+    };
+    let mut app = Reloadable::builder(&path, common::host())
+        .loading_strategy(strategy)
+        .build()
+        .unwrap();
+    assert!(app.update_with_input(&[PLUGIN_PANIC]).is_err());
+
+    // The loader would hand back the poisoned library for the same file
+    assert_eq!(app.reload_now().unwrap(), ReloadOutcome::Reloaded);
+    assert_eq!(
+        mappings_of("memfd:libplugin").len(),
+        mappings_of("memfd:").len()
+    );
+    assert!(!mappings_of("memfd:libplugin").is_empty());
+    drop(app);
+    assert!(mappings_of("memfd:").is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn io_errors_name_the_file() {
+    let dir = common::temp_dir("io-error");
+    let path = common::install_demo(&dir, 0);
+    let mut app = Reloadable::new(&path, common::host()).unwrap();
+
+    let missing = dir.join(live_reload::library_file_name("missing"));
+    match app.watch_additional_library(&missing) {
+        Err(err @ Error::Io(_)) => assert!(
+            err.to_string()
+                .starts_with(&format!("I/O error: couldn't find {}: ", missing.display())),
+            "{}",
+            err
+        ),
+        Err(err) => panic!("expected an I/O error, got {}", err),
+        Ok(()) => panic!("a missing library was loaded"),
+    }
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn the_library_sees_its_own_directory() {
+    use std::sync::mpsc::channel;
+
+    use live_reload::{DebouncedEvent, LoadingStrategy};
+
+    /// The input that makes the test plugin print its directory.
+    const PLUGIN_PRINT_DIR: u32 = 2;
+
+    let dir = common::temp_dir("plugin-dir");
+    let copies = common::temp_dir("plugin-dir-copies");
+    let path = common::install_plugin(&dir, 0);
+    let (tx, rx) = channel();
+    let strategy = LoadingStrategy {
+        load_new_first: true,
+        copy_dir: Some(copies.clone()),
+        ..LoadingStrategy::default()
+    };
+    let mut app = Reloadable::builder(&path, common::host())
+        .loading_strategy(strategy)
+        .events(rx)
+        .build()
+        .unwrap();
+    let expected = format!("Plugin dir {}.\n", dir.canonicalize().unwrap().display());
+    app.update_with_input(&[PLUGIN_PRINT_DIR]).unwrap();
+    assert!(common::take_output().contains(&expected));
+
+    // The new build is loaded from a copy somewhere else
+    let path = common::install_plugin(&dir, 1);
+    tx.send(DebouncedEvent::Write(path)).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_count(), 1);
+    assert!(app
+        .loaded_file()
+        .unwrap()
+        .starts_with(copies.canonicalize().unwrap()));
+    common::take_output();
+    app.update_with_input(&[PLUGIN_PRINT_DIR]).unwrap();
+    assert!(common::take_output().contains(&expected));
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(copies).unwrap();
+}
@@ -0,0 +1,70 @@
+//! A library for the integration tests to load next to the demo, which can
+//! be told to panic, print its directory or quit through its input.
+
+use live_reload::{live_reload, PluginInfo, ShouldQuit};
+
+#[path = "../../../demo/src/shared_api.rs"]
+mod shared_api;
+
+use shared_api::Host;
+
+/// Panic in this update.
+pub const PANIC: u32 = 1;
+/// Print the directory the library was loaded from.
+pub const PRINT_DIR: u32 = 2;
+
+live_reload! {
+    host: Host;
+    state: State;
+    input: u32;
+    info: PluginInfo;
+    init: init;
+    reload: reload;
+    update: update;
+    unload: unload;
+    deinit: deinit;
+}
+
+#[repr(C)]
+struct State {
+    counter: u32,
+    quit_at: u32,
+}
+
+fn init(host: &mut Host, state: &mut State, _: &PluginInfo) {
+    state.counter = 0;
+    (host.print)("Plugin init.\n");
+}
+
+fn reload(host: &mut Host, state: &mut State, _: &PluginInfo) {
+    (host.print)(&format!("Plugin reloaded at {}.\n", state.counter));
+}
+
+/// Any other input is the update to quit at.
+fn update(host: &mut Host, state: &mut State, input: &[u32], info: &PluginInfo) -> ShouldQuit {
+    for &event in input {
+        match event {
+            PANIC => panic!("the plugin was told to panic"),
+            PRINT_DIR => {
+                let dir = info.plugin_dir().map(|dir| dir.display().to_string());
+                (host.print)(&format!("Plugin dir {}.\n", dir.unwrap_or_default()));
+            }
+            quit_at => state.quit_at = quit_at,
+        }
+    }
+    state.counter += 1;
+    (host.print)(&format!("Plugin update {}.\n", state.counter));
+    if state.quit_at != 0 && state.counter >= state.quit_at {
+        ShouldQuit::Yes
+    } else {
+        ShouldQuit::No
+    }
+}
+
+fn unload(host: &mut Host, state: &mut State) {
+    (host.print)(&format!("Plugin unloaded at {}.\n", state.counter));
+}
+
+fn deinit(host: &mut Host, state: &mut State) {
+    (host.print)(&format!("Plugin deinit at {}.\n", state.counter));
+}
@@ -0,0 +1,212 @@
+mod common;
+
+use std::fs;
+use std::sync::mpsc::channel;
+
+use live_reload::{DebouncedEvent, Error, ReloadErrorPolicy, ReloadOutcome, Reloadable};
+
+#[test]
+fn state_survives_back_to_back_reloads() {
+    let dir = common::temp_dir("back-to-back");
+    let path = common::install_demo(&dir, 0);
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&path, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+    app.update();
+    common::take_output();
+
+    // Two saves in a row, with no update in between
+    for build in 1..=2 {
+        let path = common::install_demo(&dir, build);
+        tx.send(DebouncedEvent::Write(path)).unwrap();
+        app.reload().unwrap();
+    }
+    assert_eq!(app.reload_count(), 2);
+    assert_eq!(
+        common::take_output(),
+        "Unloaded at 2.\nReloaded at 2.\nUnloaded at 2.\nReloaded at 2.\n"
+    );
+
+    app.update();
+    assert_eq!(common::take_output(), "Counter: 4.\n");
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn watcher_errors_are_returned_from_reload() {
+    let dir = common::temp_dir("watch-error");
+    let path = common::install_demo(&dir, 0);
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&path, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+
+    let error = notify::Error::Generic("the watcher died".into());
+    tx.send(DebouncedEvent::Error(error, None)).unwrap();
+    match app.reload() {
+        Err(Error::Watch(notify::Error::Generic(message))) => {
+            assert_eq!(message, "the watcher died")
+        }
+        Err(err) => panic!("expected a watch error, got {}", err),
+        Ok(()) => panic!("the watch error was dropped"),
+    }
+    // It's only reported once
+    app.reload().unwrap();
+
+    app.set_reload_error_policy(ReloadErrorPolicy::KeepRunning);
+    let error = notify::Error::Generic("the watcher died again".into());
+    tx.send(DebouncedEvent::Error(error, None)).unwrap();
+    app.reload().unwrap();
+    assert!(matches!(app.last_error(), Some(Error::Watch(_))));
+    // Nothing was reloaded since, so the error is still there
+    app.reload().unwrap();
+    assert!(matches!(app.last_error(), Some(Error::Watch(_))));
+    // Until the library is reloaded
+    let path = common::install_demo(&dir, 1);
+    tx.send(DebouncedEvent::Write(path)).unwrap();
+    app.reload().unwrap();
+    assert!(app.last_error().is_none());
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn renaming_a_build_over_the_library_reloads() {
+    let dir = common::temp_dir("rename-over");
+    let path = common::install_demo(&dir, 0);
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&path, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+
+    // The build writes a temporary file and renames it over the library, so
+    // the temporary file is already gone when the events arrive
+    let temp = dir.join("reloadable.tmp");
+    common::install_demo(&dir, 1);
+    tx.send(DebouncedEvent::Create(temp.clone())).unwrap();
+    tx.send(DebouncedEvent::Rename(temp, path.clone())).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_count(), 1);
+
+    // A watcher that doesn't pair up the rename reports it as a new file
+    common::install_demo(&dir, 2);
+    tx.send(DebouncedEvent::NoticeRemove(path.clone())).unwrap();
+    tx.send(DebouncedEvent::Create(path)).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_count(), 2);
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+#[cfg(unix)]
+fn renaming_a_link_over_the_library_loads_its_new_target() {
+    use std::os::unix::fs::symlink;
+
+    let dir = common::temp_dir("rename-link");
+    let first = common::install_demo(&dir, 0);
+    let build_1 = dir.join("build-1");
+    fs::rename(&first, &build_1).unwrap();
+    let link = dir.join("current");
+    symlink(&build_1, &link).unwrap();
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&link, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+
+    // Point the library's path at a new build by renaming a new link over it
+    let second = common::install_demo(&dir, 1);
+    let build_2 = dir.join("build-2");
+    fs::rename(&second, &build_2).unwrap();
+    let temp_link = dir.join("current.tmp");
+    symlink(&build_2, &temp_link).unwrap();
+    fs::rename(&temp_link, &link).unwrap();
+    tx.send(DebouncedEvent::Rename(temp_link, link)).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_count(), 1);
+
+    // The new target is the library from now on
+    let third = common::install_demo(&dir, 2);
+    fs::rename(&third, &build_2).unwrap();
+    tx.send(DebouncedEvent::Write(build_2)).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_count(), 2);
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reinstalling_the_same_build_is_unchanged() {
+    let dir = common::temp_dir("same-build");
+    let path = common::install_demo(&dir, 0);
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&path, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+    app.update();
+    common::take_output();
+
+    // A rebuild that produced exactly the same library
+    let path = common::install_demo(&dir, 0);
+    tx.send(DebouncedEvent::Write(path)).unwrap();
+    app.reload().unwrap();
+    assert_eq!(app.reload_now().unwrap(), ReloadOutcome::Unchanged);
+    assert_eq!(app.reload_count(), 0);
+    assert_eq!(common::take_output(), "");
+
+    app.update();
+    assert_eq!(common::take_output(), "Counter: 4.\n");
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn companions_reload_after_the_library_and_only_all_together() {
+    let dir = common::temp_dir("companion");
+    let path = common::install_demo(&dir, 0);
+    let plugin = common::install_plugin(&dir, 0);
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&path, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+    app.watch_additional_library(&plugin).unwrap();
+    app.update();
+    app.update();
+    assert_eq!(
+        common::take_output(),
+        "Init! Counter: 0.\nPlugin init.\nCounter: 2.\nCounter: 4.\n"
+    );
+
+    // Only the library is updated, so the two States can be told apart
+    let plugin = common::install_plugin(&dir, 1);
+    tx.send(DebouncedEvent::Write(plugin.clone())).unwrap();
+    app.reload().unwrap();
+    assert_eq!(
+        common::take_output(),
+        "Unloaded at 4.\nPlugin unloaded at 0.\nReloaded at 4.\nPlugin reloaded at 0.\n"
+    );
+
+    // Neither is unloaded if either one fails to load
+    fs::write(dir.join("broken"), "not a library").unwrap();
+    fs::rename(dir.join("broken"), &plugin).unwrap();
+    tx.send(DebouncedEvent::Write(plugin.clone())).unwrap();
+    assert!(app.reload().is_err());
+    common::install_plugin(&dir, 2);
+    fs::write(dir.join("broken"), "not a library").unwrap();
+    fs::rename(dir.join("broken"), &path).unwrap();
+    tx.send(DebouncedEvent::Write(path.clone())).unwrap();
+    assert!(app.reload().is_err());
+    assert_eq!(common::take_output(), "");
+    app.update();
+    assert_eq!(common::take_output(), "Counter: 6.\n");
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
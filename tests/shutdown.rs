@@ -0,0 +1,35 @@
+mod common;
+
+use std::fs;
+use std::sync::mpsc::channel;
+
+use live_reload::{DebouncedEvent, Reloadable};
+
+#[test]
+fn force_quit_only_deinits_once() {
+    let dir = common::temp_dir("force-quit");
+    let path = common::install_demo(&dir, 0);
+    let (tx, rx) = channel();
+    let mut app = Reloadable::builder(&path, common::host())
+        .events(rx)
+        .build()
+        .unwrap();
+    app.update();
+
+    app.force_quit().unwrap();
+    app.force_quit().unwrap();
+    assert!(app.is_dead());
+    assert!(!app.is_loaded());
+
+    // Nothing brings the library back, and dropping doesn't deinit again
+    let path = common::install_demo(&dir, 1);
+    tx.send(DebouncedEvent::Write(path)).unwrap();
+    app.reload().unwrap();
+    app.update();
+    drop(app);
+
+    let output = common::take_output();
+    assert_eq!(output.matches("Goodbye!").count(), 1, "{}", output);
+    assert!(output.ends_with("Goodbye! Reached a final value of 2.\n"));
+    fs::remove_dir_all(dir).unwrap();
+}
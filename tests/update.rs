@@ -0,0 +1,102 @@
+mod common;
+
+use std::fs;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::channel;
+
+use live_reload::{Error, ReloadOutcome, Reloadable, ShouldQuit};
+
+/// The input that makes the test plugin panic.
+const PLUGIN_PANIC: u32 = 1;
+
+fn other_print(_: &str) {}
+
+#[test]
+fn update_with_host_gives_the_host_back_after_a_panic() {
+    let dir = common::temp_dir("update-with-host");
+    let path = common::install_demo(&dir, 0);
+    let mut app = Reloadable::new(&path, common::host()).unwrap();
+    let own_print = app.host().print as usize;
+    app.set_before_update(|_| panic!("the host's callback panicked"));
+
+    let mut other = common::Host { print: other_print };
+    let result = catch_unwind(AssertUnwindSafe(|| app.update_with_host(&mut other)));
+    assert!(result.is_err());
+    assert_eq!(app.host().print as usize, own_print);
+    assert_eq!(other.print as usize, other_print as *const () as usize);
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reset_runs_init_on_a_zeroed_state() {
+    let dir = common::temp_dir("reset");
+    let path = common::install_demo(&dir, 0);
+    let mut app = Reloadable::new(&path, common::host()).unwrap();
+    app.update();
+    app.update();
+    assert_eq!(app.state_bytes(), &4usize.to_ne_bytes()[..]);
+    common::take_output();
+
+    assert_eq!(app.reset().unwrap(), ShouldQuit::No);
+    assert_eq!(common::take_output(), "Init! Counter: 0.\n");
+    assert_eq!(app.state_bytes(), &0usize.to_ne_bytes()[..]);
+    app.update();
+    assert_eq!(common::take_output(), "Counter: 2.\n");
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reloading_after_a_panic_skips_unload() {
+    let dir = common::temp_dir("update-panic");
+    let path = common::install_plugin(&dir, 0);
+    let mut app = Reloadable::new(&path, common::host()).unwrap();
+    app.update();
+    common::take_output();
+
+    match app.update_with_input(&[PLUGIN_PANIC]) {
+        Err(Error::Panicked(message)) => assert_eq!(message, "the plugin was told to panic"),
+        other => panic!(
+            "expected a panic, got {:?}",
+            other.map_err(|err| err.to_string())
+        ),
+    }
+    assert!(app.is_poisoned());
+
+    // The file is unchanged, but a poisoned library is reloaded anyway
+    assert_eq!(app.reload_now().unwrap(), ReloadOutcome::Reloaded);
+    assert_eq!(common::take_output(), "Plugin reloaded at 1.\n");
+    assert!(!app.is_poisoned());
+    app.update();
+    assert_eq!(common::take_output(), "Plugin update 2.\n");
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn update_n_stops_at_the_update_that_quits() {
+    let dir = common::temp_dir("update-n");
+    let path = common::install_plugin(&dir, 0);
+    let mut app = Reloadable::new(&path, common::host()).unwrap();
+    // Any other input is the update for the plugin to quit at
+    app.update_with_input(&[4u32]).unwrap();
+    common::take_output();
+
+    assert_eq!(app.update_n(10), ShouldQuit::Yes);
+    assert_eq!(
+        common::take_output(),
+        "Plugin update 2.\nPlugin update 3.\nPlugin update 4.\n"
+    );
+
+    // The host loops itself to call this before every update
+    let (tx, rx) = channel();
+    app.set_before_update(move |_| tx.send(()).unwrap());
+    assert_eq!(app.update_n(10), ShouldQuit::Yes);
+    assert_eq!(common::take_output(), "Plugin update 5.\n");
+    assert_eq!(rx.try_iter().count(), 1);
+    assert_eq!(app.update_n(0), ShouldQuit::No);
+    assert_eq!(rx.try_iter().count(), 0);
+    drop(app);
+    fs::remove_dir_all(dir).unwrap();
+}
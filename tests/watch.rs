@@ -0,0 +1,44 @@
+mod common;
+
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use live_reload::Reloadable;
+
+/// Call `reload` until the library was reloaded `count` times in total, or
+/// give up after a while.
+fn reload_until(app: &mut Reloadable<common::Host>, count: usize) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        app.reload().unwrap();
+        if app.reload_count() >= count {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[test]
+fn builds_reload_after_the_directory_is_recreated() {
+    let root = common::temp_dir("recreate-dir");
+    let dir = root.join("debug");
+    fs::create_dir(&dir).unwrap();
+    let path = common::install_demo(&dir, 0);
+    let mut app = Reloadable::new(&path, common::host()).unwrap();
+
+    // Like `cargo clean` followed by a build
+    fs::remove_dir_all(&dir).unwrap();
+    thread::sleep(Duration::from_millis(1500));
+    app.reload().unwrap();
+    fs::create_dir(&dir).unwrap();
+    common::install_demo(&dir, 1);
+    assert!(reload_until(&mut app, 1), "the first build wasn't loaded");
+
+    // The directory is watched again, so the next build reloads too
+    common::install_demo(&dir, 2);
+    assert!(reload_until(&mut app, 2), "the next build wasn't noticed");
+    drop(app);
+    fs::remove_dir_all(root).unwrap();
+}